@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use tokio::{task::spawn_blocking, time::interval};
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    parse_paras::PARAS,
+};
+
+/// save all updated mindmap in a blocking thread, so file writes do not stall the async workers, return (saved number, failed number)
+pub async fn save_all() -> (usize, usize) {
+    match spawn_blocking(|| DATA.write().unwrap().save_mindmap()).await {
+        Ok(r) => r,
+        Err(e) => {
+            event!(Level::ERROR, "save mindmap: {}", e);
+            (0, 1)
+        },
+    }
+}
+
+/// Periodically save all updated mindmap to local, interval is `-s` seconds, 0 means disable
+pub async fn autosave() {
+    if PARAS.autosave == 0 {
+        return
+    }
+    let mut ticker = interval(Duration::from_secs(PARAS.autosave));
+    ticker.tick().await; // the first tick completes immediately
+    loop {
+        ticker.tick().await;
+        let (saved, failed) = save_all().await;
+        if saved > 0 || failed > 0 {
+            event!(Level::INFO, "autosave: {} mindmap saved, {} failed", saved, failed);
+        }
+    }
+}
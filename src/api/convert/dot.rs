@@ -0,0 +1,128 @@
+use crate::{
+    escape::escape_dot,
+    model::{MindMapData, NodeObj},
+};
+
+use super::{font_size, is_bold};
+
+/// css color as Graphviz color, `#rrggbb` and color names are the same, other css colors are dropped
+fn color(css: &str) -> Option<String> {
+    let css = css.trim();
+    if (css.starts_with('#') && css.len() > 1) || (!css.is_empty() && css.chars().all(|c| c.is_ascii_alphabetic())) {
+        Some(escape_dot(css))
+    } else {
+        None
+    }
+}
+
+/// attributes of one node, label is the icons and topic, node style becomes font and fill color, font size and bold font
+/// hyperlink is `URL` and note is `tooltip`, both are used by svg output
+fn node_attributes(node: &NodeObj, root: bool) -> String {
+    let mut label = node.icons.join(" ");
+    if !label.is_empty() {
+        label.push(' ');
+    }
+    label.push_str(&node.topic);
+    let mut attributes = vec![format!("label=\"{}\"", escape_dot(&label))];
+    let mut size = root.then_some(20);
+    if let Some(style) = &node.style {
+        if let Some(c) = style.color.as_deref().and_then(color) {
+            attributes.push(format!("fontcolor=\"{}\"", c));
+        }
+        if let Some(c) = style.background.as_deref().and_then(color) {
+            attributes.push(format!("fillcolor=\"{}\"", c));
+        }
+        if let Some(s) = style.font_size.as_deref().and_then(font_size) {
+            size = Some(s);
+        }
+        if style.font_weight.as_deref().is_some_and(is_bold) {
+            attributes.push("fontname=\"Helvetica-Bold\"".to_string());
+        }
+    }
+    if let Some(s) = size {
+        attributes.push(format!("fontsize={}", s));
+    }
+    if root {
+        attributes.push("penwidth=2".to_string());
+    }
+    if let Some(link) = &node.hyper_link {
+        attributes.push(format!("URL=\"{}\"", escape_dot(link)));
+    }
+    if let Some(note) = &node.note {
+        attributes.push(format!("tooltip=\"{}\"", escape_dot(note)));
+    }
+    attributes.join(", ")
+}
+
+/// write node and descendants with the edges to their children, `depth` 0 is the root
+/// `branch` is the edge color of this main branch, main branches use their branch color or the theme palette
+fn write_node(node: &NodeObj, depth: usize, branch: Option<&str>, palette: &[String], out: &mut String) {
+    out.push_str(&format!("  \"{}\" [{}];\n", escape_dot(&node.id), node_attributes(node, depth == 0)));
+    for (i, child) in node.children.iter().enumerate() {
+        let main = (depth == 0 && !palette.is_empty()).then(|| palette[i % palette.len()].as_str());
+        let branch = child.branch_color.as_deref().or(main).or(branch);
+        out.push_str(&format!("  \"{}\" -> \"{}\"", escape_dot(&node.id), escape_dot(&child.id)));
+        if let Some(c) = branch.and_then(color) {
+            out.push_str(&format!(" [color=\"{}\"]", c));
+        }
+        out.push_str(";\n");
+        write_node(child, depth + 1, branch, palette, out);
+    }
+}
+
+/// convert mindmap to a Graphviz DOT digraph from left to right, `title` is the graph label
+/// arrows are dashed edges with their labels that do not change the layout, summaries are text nodes linked by dotted edges from their children
+pub fn to_dot(data: &MindMapData, title: &str) -> String {
+    let mut out = format!("digraph \"{}\" {{\n", escape_dot(title));
+    out.push_str(&format!("  graph [rankdir=LR, label=\"{}\", labelloc=t, fontname=\"Helvetica\"];\n", escape_dot(title)));
+    out.push_str("  node [shape=box, style=\"rounded,filled\", fillcolor=\"white\", fontname=\"Helvetica\"];\n");
+    out.push_str("  edge [arrowhead=none];\n");
+    let palette = data.theme.as_ref().map(|t| t.palette.as_slice()).unwrap_or_default();
+    write_node(&data.node_data, 0, None, palette, &mut out);
+    for arrow in &data.arrows {
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\" [style=dashed, constraint=false, arrowhead=normal{}{}];\n",
+            escape_dot(&arrow.from),
+            escape_dot(&arrow.to),
+            if arrow.bidirectional == Some(true) { ", dir=both, arrowtail=normal" } else { "" },
+            if arrow.label.is_empty() { String::new() } else { format!(", label=\"{}\"", escape_dot(&arrow.label)) },
+        ));
+    }
+    for summary in &data.summaries {
+        let id = format!("summary:{}", summary.id);
+        out.push_str(&format!("  \"{}\" [label=\"{}\", shape=plaintext, style=\"\"];\n", escape_dot(&id), escape_dot(&summary.label)));
+        let children = data.node_data.find(&summary.parent).and_then(|p| p.children.get(summary.start..=summary.end));
+        for child in children.unwrap_or_default() {
+            out.push_str(&format!("  \"{}\" -> \"{}\" [style=dotted];\n", escape_dot(&child.id), escape_dot(&id)));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nodes_edges_arrows_and_summaries() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"say \"hi\"\nthere","children":[
+            {"id":"a","topic":"styled","icons":["💡"],"hyperLink":"https://example.com","note":"a\\b","style":{"color":"#f00","background":"rgb(1,2,3)","fontSize":"24px","fontWeight":"bold"},"children":[
+                {"id":"a1","topic":"x"},{"id":"a2","topic":"y"}
+            ]},
+            {"id":"b","topic":"plain","branchColor":"green"}
+        ]},"arrows":[{"id":"r","label":"see","from":"a1","to":"b","bidirectional":true}],
+        "summaries":[{"id":"s","label":"both","parent":"a","start":0,"end":1}],
+        "theme":{"name":"t","palette":["#111111","#222222"],"cssVar":{}}}"##).unwrap();
+        let dot = to_dot(&data, "my \"map\"");
+        assert!(dot.starts_with("digraph \"my \\\"map\\\"\" {\n"));
+        assert!(dot.contains("  \"root\" [label=\"say \\\"hi\\\"\\nthere\", fontsize=20, penwidth=2];\n"));
+        assert!(dot.contains("  \"a\" [label=\"💡 styled\", fontcolor=\"#f00\", fontname=\"Helvetica-Bold\", fontsize=24, URL=\"https://example.com\", tooltip=\"a\\\\b\"];\n"));
+        assert!(dot.contains("  \"root\" -> \"a\" [color=\"#111111\"];\n  \"a\" [")); // main branch color from the palette
+        assert!(dot.contains("  \"a\" -> \"a1\" [color=\"#111111\"];\n")); // inherited by descendants
+        assert!(dot.contains("  \"root\" -> \"b\" [color=\"green\"];\n")); // own branch color first
+        assert!(dot.contains("  \"a1\" -> \"b\" [style=dashed, constraint=false, arrowhead=normal, dir=both, arrowtail=normal, label=\"see\"];\n"));
+        assert!(dot.contains("  \"summary:s\" [label=\"both\", shape=plaintext, style=\"\"];\n  \"a1\" -> \"summary:s\" [style=dotted];\n  \"a2\" -> \"summary:s\" [style=dotted];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
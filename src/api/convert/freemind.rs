@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+
+use roxmltree::{Document, Node};
+use serde_json::Map;
+
+use crate::{
+    error::MyError,
+    escape::escape_xml,
+    model::{new_node_id, Arrow, Delta, MindMapData, NodeObj, NodeStyle, Tag},
+};
+
+use super::{font_size, is_bold};
+
+/// FreeMind builtin icons and the emoji shown by mind-elixir, other emoji are written as Freeplane `emoji-<code points>` icons
+const ICONS: [(&str, &str); 36] = [
+    ("idea", "💡"),
+    ("help", "❓"),
+    ("yes", "❗"),
+    ("info", "ℹ️"),
+    ("messagebox_warning", "⚠️"),
+    ("button_ok", "✅"),
+    ("button_cancel", "❌"),
+    ("stop-sign", "🛑"),
+    ("flag", "🚩"),
+    ("bookmark", "🔖"),
+    ("list", "📋"),
+    ("calendar", "📅"),
+    ("clock", "⏰"),
+    ("bell", "🔔"),
+    ("launch", "🚀"),
+    ("password", "🔑"),
+    ("attach", "📎"),
+    ("Mail", "✉️"),
+    ("pencil", "✏️"),
+    ("gohome", "🏠"),
+    ("ksmiletris", "😊"),
+    ("smiley-neutral", "😐"),
+    ("smily_bad", "😞"),
+    ("smiley-angry", "😠"),
+    ("go", "🟢"),
+    ("prepare", "🟡"),
+    ("stop", "🔴"),
+    ("full-1", "1️⃣"),
+    ("full-2", "2️⃣"),
+    ("full-3", "3️⃣"),
+    ("full-4", "4️⃣"),
+    ("full-5", "5️⃣"),
+    ("full-6", "6️⃣"),
+    ("full-7", "7️⃣"),
+    ("full-8", "8️⃣"),
+    ("full-9", "9️⃣"),
+];
+
+/// attribute name used for mind-elixir tags, FreeMind has no tags
+const TAG_ATTRIBUTE: &str = "tag";
+
+/// `BUILTIN` name of a mind-elixir icon
+fn icon_name(icon: &str) -> String {
+    match ICONS.iter().find(|(_, e)| *e == icon) {
+        Some((name, _)) => name.to_string(),
+        None => format!("emoji-{}", icon.chars().map(|c| format!("{:X}", c as u32)).collect::<Vec<String>>().join("-")),
+    }
+}
+
+/// mind-elixir icon of a `BUILTIN` name, unknown names are kept as they are
+fn icon_emoji(name: &str) -> String {
+    if let Some((_, emoji)) = ICONS.iter().find(|(n, _)| *n == name) {
+        return emoji.to_string()
+    }
+    if let Some(codes) = name.strip_prefix("emoji-") {
+        let emoji: Option<String> = codes.split('-').map(|c| u32::from_str_radix(c, 16).ok().and_then(char::from_u32)).collect();
+        if let Some(e) = emoji {
+            return e
+        }
+    }
+    name.to_string()
+}
+
+/// FreeMind node id, must start with a letter
+fn freemind_id(id: &str) -> String {
+    format!("ID_{}", id)
+}
+
+/// mind-elixir node id of a FreeMind node id
+fn node_id(id: &str) -> String {
+    id.strip_prefix("ID_").unwrap_or(id).to_string()
+}
+
+/// write node and descendants as `<node>`, `positions` are sides of children, only given for the root
+/// arrows are written as `<arrowlink>` of their start node
+fn write_node(node: &NodeObj, position: Option<u8>, positions: &[u8], arrows: &HashMap<&str, Vec<&Arrow>>, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}<node TEXT=\"{}\" ID=\"{}\"", indent, escape_xml(&node.topic), escape_xml(&freemind_id(&node.id))));
+    match position {
+        Some(0) => out.push_str(" POSITION=\"left\""),
+        Some(_) => out.push_str(" POSITION=\"right\""),
+        None => (),
+    }
+    if node.expanded == Some(false) && !node.children.is_empty() {
+        out.push_str(" FOLDED=\"true\"");
+    }
+    if let Some(link) = &node.hyper_link {
+        out.push_str(&format!(" LINK=\"{}\"", escape_xml(link)));
+    }
+    let mut font = String::new();
+    if let Some(style) = &node.style {
+        if let Some(color) = &style.color {
+            out.push_str(&format!(" COLOR=\"{}\"", escape_xml(color)));
+        }
+        if let Some(background) = &style.background {
+            out.push_str(&format!(" BACKGROUND_COLOR=\"{}\"", escape_xml(background)));
+        }
+        if let Some(size) = style.font_size.as_deref().and_then(font_size) {
+            font.push_str(&format!(" SIZE=\"{}\"", size));
+        }
+        if style.font_weight.as_deref().is_some_and(is_bold) {
+            font.push_str(" BOLD=\"true\"");
+        }
+    }
+    let mut inner = String::new();
+    if !font.is_empty() {
+        inner.push_str(&format!("{}  <font{}/>\n", indent, font));
+    }
+    for icon in &node.icons {
+        inner.push_str(&format!("{}  <icon BUILTIN=\"{}\"/>\n", indent, escape_xml(&icon_name(icon))));
+    }
+    for tag in &node.tags {
+        inner.push_str(&format!("{}  <attribute NAME=\"{}\" VALUE=\"{}\"/>\n", indent, TAG_ATTRIBUTE, escape_xml(tag.text())));
+    }
+    if let Some(note) = &node.note {
+        inner.push_str(&format!("{}  <richcontent TYPE=\"NOTE\"><html><head></head><body>", indent));
+        for line in note.lines() {
+            inner.push_str(&format!("<p>{}</p>", escape_xml(line)));
+        }
+        inner.push_str("</body></html></richcontent>\n");
+    }
+    for arrow in arrows.get(node.id.as_str()).into_iter().flatten() {
+        inner.push_str(&format!(
+            "{}  <arrowlink ID=\"{}\" DESTINATION=\"{}\" STARTINCLINATION=\"{};{};\" ENDINCLINATION=\"{};{};\" STARTARROW=\"{}\" ENDARROW=\"DEFAULT\"",
+            indent,
+            escape_xml(&arrow.id),
+            escape_xml(&freemind_id(&arrow.to)),
+            arrow.delta1.x, arrow.delta1.y,
+            arrow.delta2.x, arrow.delta2.y,
+            if arrow.bidirectional == Some(true) { "DEFAULT" } else { "NONE" },
+        ));
+        if !arrow.label.is_empty() {
+            inner.push_str(&format!(" MIDDLE_LABEL=\"{}\"", escape_xml(&arrow.label)));
+        }
+        inner.push_str("/>\n");
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        write_node(child, positions.get(i).copied(), &[], arrows, depth + 1, &mut inner);
+    }
+    if inner.is_empty() {
+        out.push_str("/>\n");
+    } else {
+        out.push_str(&format!(">\n{}{}</node>\n", inner, indent));
+    }
+}
+
+/// convert mindmap to FreeMind `.mm`, also readable by Freeplane
+/// main nodes get `POSITION` of their side, summaries have no FreeMind equivalent and are dropped
+pub fn to_freemind(data: &MindMapData) -> String {
+    let mut arrows: HashMap<&str, Vec<&Arrow>> = HashMap::new();
+    for arrow in &data.arrows {
+        arrows.entry(arrow.from.as_str()).or_default().push(arrow);
+    }
+    let mut out = String::from("<map version=\"1.0.1\">\n");
+    write_node(&data.node_data, None, &data.main_sides(), &arrows, 0, &mut out);
+    out.push_str("</map>\n");
+    out
+}
+
+/// append text of html `node` to `text`, whitespace is collapsed, block elements start new lines
+fn html_text(node: Node, text: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            let mut last_space = text.ends_with(' ');
+            for c in child.text().unwrap_or_default().chars() {
+                if c.is_whitespace() {
+                    if !last_space {
+                        text.push(' ');
+                    }
+                    last_space = true;
+                } else {
+                    text.push(c);
+                    last_space = false;
+                }
+            }
+        } else if child.is_element() {
+            let block = matches!(child.tag_name().name(), "p" | "div" | "li" | "br" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6");
+            if block {
+                text.push('\n');
+            }
+            html_text(child, text);
+            if block {
+                text.push('\n');
+            }
+        }
+    }
+}
+
+/// plain text of `<richcontent>`, html paragraphs become lines, Freeplane plain text or markdown is kept as it is
+fn rich_text(rich: Node) -> String {
+    match rich.descendants().find(|d| d.has_tag_name("body")) {
+        Some(body) => {
+            let mut text = String::new();
+            html_text(body, &mut text);
+            text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<&str>>().join("\n")
+        },
+        None => rich.descendants().filter(|d| d.is_text()).map(|d| d.text().unwrap_or_default()).collect::<String>().trim().to_string(),
+    }
+}
+
+/// x and y of `STARTINCLINATION` or `ENDINCLINATION`, e.g. `40;-20;`
+fn inclination(value: Option<&str>) -> Delta {
+    let mut xy = value.unwrap_or_default().split(';').map(|v| v.trim().parse::<f64>().ok());
+    match (xy.next().flatten(), xy.next().flatten()) {
+        (Some(x), Some(y)) => Delta{x, y},
+        _ => Delta::default(),
+    }
+}
+
+/// read one `<node>` and its children, `depth` is 0 for the root, `POSITION` is only read for main nodes
+/// `<arrowlink>` are collected into `arrows`, `seen` ids are given new ones before arrows take the id of their start node
+fn read_node(element: Node, depth: usize, seen: &mut HashSet<String>, arrows: &mut Vec<Arrow>) -> NodeObj {
+    let id = match element.attribute("ID").map(node_id) {
+        Some(id) if !id.is_empty() && !seen.contains(&id) => id,
+        _ => new_node_id(),
+    };
+    seen.insert(id.clone());
+    let mut node = NodeObj{id, ..Default::default()};
+    node.topic = match element.attribute("TEXT") {
+        Some(t) => t.to_string(),
+        None => element.children().find(|c| c.has_tag_name("richcontent") && c.attribute("TYPE") == Some("NODE")).map(rich_text).unwrap_or_default(),
+    };
+    node.hyper_link = element.attribute("LINK").map(|l| l.to_string());
+    if element.attribute("FOLDED") == Some("true") {
+        node.expanded = Some(false);
+    }
+    if depth == 1 {
+        node.direction = match element.attribute("POSITION") {
+            Some("left") => Some(0),
+            Some("right") => Some(1),
+            _ => None,
+        };
+    }
+    let mut style = NodeStyle{
+        color:      element.attribute("COLOR").map(|c| c.to_string()),
+        background: element.attribute("BACKGROUND_COLOR").map(|c| c.to_string()),
+        ..Default::default()
+    };
+    for child in element.children().filter(|c| c.is_element()) {
+        match child.tag_name().name() {
+            "font" => {
+                style.font_size = child.attribute("SIZE").map(|s| format!("{}px", s));
+                if child.attribute("BOLD") == Some("true") {
+                    style.font_weight = Some("bold".to_string());
+                }
+            },
+            "icon" => if let Some(name) = child.attribute("BUILTIN") {
+                node.icons.push(icon_emoji(name));
+            },
+            "attribute" if child.attribute("NAME") == Some(TAG_ATTRIBUTE) => if let Some(value) = child.attribute("VALUE") {
+                node.tags.push(Tag::Text(value.to_string()));
+            },
+            "richcontent" if child.attribute("TYPE") == Some("NOTE") => {
+                node.note = Some(rich_text(child)).filter(|n| !n.is_empty());
+            },
+            "arrowlink" => if let Some(destination) = child.attribute("DESTINATION") {
+                arrows.push(Arrow{
+                    id:            child.attribute("ID").map(|i| i.to_string()).unwrap_or_else(new_node_id),
+                    label:         child.attribute("MIDDLE_LABEL").unwrap_or_default().to_string(),
+                    from:          node.id.clone(),
+                    to:            node_id(destination),
+                    delta1:        inclination(child.attribute("STARTINCLINATION")),
+                    delta2:        inclination(child.attribute("ENDINCLINATION")),
+                    bidirectional: child.attribute("STARTARROW").filter(|a| !a.eq_ignore_ascii_case("none")).map(|_| true),
+                    style:         None,
+                    extra:         Map::new(),
+                });
+            },
+            "node" => node.children.push(read_node(child, depth + 1, seen, arrows)),
+            _ => (),
+        }
+    }
+    if style.color.is_some() || style.background.is_some() || style.font_size.is_some() || style.font_weight.is_some() {
+        node.style = Some(style);
+    }
+    node
+}
+
+/// parse FreeMind or Freeplane `.mm`, `POSITION` of main nodes is kept in side mode
+/// arrows to nodes that do not exist are dropped, duplicate node ids get new ones, arrows to them go to the first node
+pub fn from_freemind(text: &str) -> Result<MindMapData, MyError> {
+    let doc = match Document::parse(text) {
+        Ok(d) => d,
+        Err(e) => return Err(MyError::ValidationError{reason: format!("invalid mm: {}", e)}),
+    };
+    let map = doc.root_element();
+    if !map.has_tag_name("map") {
+        return Err(MyError::ValidationError{reason: format!("invalid mm: root element is <{}>, not <map>", map.tag_name().name())})
+    }
+    let root = match map.children().find(|c| c.has_tag_name("node")) {
+        Some(r) => r,
+        None => return Err(MyError::ValidationError{reason: "no node found in mm".to_string()}),
+    };
+    let mut arrows = Vec::new();
+    let node_data = read_node(root, 0, &mut HashSet::new(), &mut arrows);
+    let mut data = MindMapData{node_data, arrows, summaries: Vec::new(), direction: Some(2), theme: None, extra: Map::new()};
+    let ids: HashSet<String> = data.node_data.descendants().iter().map(|n| n.id.clone()).collect();
+    data.arrows.retain(|a| ids.contains(&a.from) && ids.contains(&a.to));
+    data.validate()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_sides_and_arrows() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"root","children":[
+            {"id":"a","topic":"left","direction":0,"expanded":false,"icons":["💡","🦀"],"children":[{"id":"a1","topic":"hidden"}]},
+            {"id":"b","topic":"right","direction":1,"hyperLink":"https://example.com/?a=1&b=2","tags":["t"],"note":"line 1\nline 2"}
+        ]},"arrows":[{"id":"r1","label":"to <b>","from":"a1","to":"b","delta1":{"x":10,"y":-20},"delta2":{"x":30,"y":40},"bidirectional":true}],"direction":2}"##).unwrap();
+        let mm = to_freemind(&data);
+        assert!(mm.contains("POSITION=\"left\"") && mm.contains("POSITION=\"right\"") && mm.contains("FOLDED=\"true\""));
+        let back = from_freemind(&mm).unwrap();
+        assert_eq!(serde_json::to_value(&back.node_data).unwrap(), serde_json::to_value(&data.node_data).unwrap());
+        assert_eq!(serde_json::to_value(&back.arrows).unwrap(), serde_json::to_value(&data.arrows).unwrap());
+        assert_eq!(back.main_sides(), [0, 1]);
+    }
+
+    #[test]
+    fn duplicate_ids_keep_arrow_ends() {
+        let mm = r#"<map version="1.0.1"><node ID="ID_root" TEXT="root">
+            <node ID="ID_a" TEXT="first"/>
+            <node ID="ID_a" TEXT="second"><arrowlink DESTINATION="ID_b"/></node>
+            <node ID="ID_b" TEXT="third"><arrowlink DESTINATION="ID_a"/><arrowlink DESTINATION="ID_missing"/></node>
+        </node></map>"#;
+        let data = from_freemind(mm).unwrap();
+        let children = &data.node_data.children;
+        assert_eq!(children[0].id, "a");
+        assert_ne!(children[1].id, "a");
+        let ends: Vec<(&str, &str)> = data.arrows.iter().map(|a| (a.from.as_str(), a.to.as_str())).collect();
+        assert_eq!(ends, [(children[1].id.as_str(), "b"), ("b", "a")]);
+    }
+}
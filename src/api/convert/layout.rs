@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+
+use crate::model::{MindMapData, NodeObj};
+
+use super::{font_size, is_bold};
+
+/// css variables of the mind-elixir Latte theme, used for variables missing in a light theme
+const LATTE: [(&str, &str); 17] = [
+    ("--node-gap-x", "30px"),
+    ("--node-gap-y", "10px"),
+    ("--main-gap-x", "65px"),
+    ("--main-gap-y", "45px"),
+    ("--root-radius", "30px"),
+    ("--main-radius", "20px"),
+    ("--root-color", "#ffffff"),
+    ("--root-bgcolor", "#4c4f69"),
+    ("--root-border-color", "rgba(0, 0, 0, 0)"),
+    ("--main-color", "#444446"),
+    ("--main-bgcolor", "#ffffff"),
+    ("--topic-padding", "3px"),
+    ("--color", "#777777"),
+    ("--bgcolor", "#f6f6f6"),
+    ("--selected", "#4dc4ff"),
+    ("--accent-color", "#e64553"),
+    ("--map-padding", "50px"),
+];
+
+/// css variables of the mind-elixir Dark theme, used for variables missing in a dark theme
+const DARK: [(&str, &str); 17] = [
+    ("--node-gap-x", "30px"),
+    ("--node-gap-y", "10px"),
+    ("--main-gap-x", "65px"),
+    ("--main-gap-y", "45px"),
+    ("--root-radius", "30px"),
+    ("--main-radius", "20px"),
+    ("--root-color", "#ffffff"),
+    ("--root-bgcolor", "#2d3748"),
+    ("--root-border-color", "rgba(255, 255, 255, 0.1)"),
+    ("--main-color", "#ffffff"),
+    ("--main-bgcolor", "#4c4f69"),
+    ("--topic-padding", "3px"),
+    ("--color", "#cccccc"),
+    ("--bgcolor", "#252526"),
+    ("--selected", "#4dc4ff"),
+    ("--accent-color", "#789AFA"),
+    ("--map-padding", "50px 80px"),
+];
+
+/// branch colors of the mind-elixir Latte theme
+const PALETTE: [&str; 10] = ["#dd7878", "#ea76cb", "#8839ef", "#e64553", "#fe640b", "#df8e1d", "#40a02b", "#209fb5", "#1e66f5", "#7287fd"];
+
+/// advance width of ascii 32..=126 in Helvetica, 1/1000 em
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// font family of the mind-elixir map container
+pub const FONT_FAMILY: &str = "-apple-system, BlinkMacSystemFont, 'Helvetica Neue', 'PingFang SC', 'Microsoft YaHei', 'Source Han Sans SC', 'Noto Sans CJK SC', 'Noto Sans SC', 'WenQuanYi Micro Hei', sans-serif";
+
+/// font size of tags, arrow and summary labels
+pub const TAG_FONT_SIZE: f64 = 12.0;
+pub const LABEL_FONT_SIZE: f64 = 15.0;
+
+/// theme of the mindmap with default values, css variables and branch colors
+pub struct ThemeVars {
+    vars:        BTreeMap<String, String>,
+    pub palette: Vec<String>,
+}
+
+impl ThemeVars {
+    /// theme of mindmap data, Latte if no theme, missing variables come from Latte or Dark by theme type
+    pub fn new(data: &MindMapData) -> Self {
+        let dark = data.theme.as_ref().is_some_and(|t| t.kind.as_deref() == Some("dark"));
+        let mut vars: BTreeMap<String, String> = (if dark { DARK } else { LATTE }).iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let mut palette: Vec<String> = PALETTE.iter().map(|c| c.to_string()).collect();
+        if let Some(theme) = &data.theme {
+            vars.extend(theme.css_var.iter().map(|(k, v)| (k.clone(), v.clone())));
+            if !theme.palette.is_empty() {
+                palette = theme.palette.clone();
+            }
+        }
+        ThemeVars{vars, palette}
+    }
+
+    /// value of css variable, e.g. `--color`
+    pub fn var(&self, name: &str) -> &str {
+        self.vars.get(name).map_or("", |v| v.trim())
+    }
+
+    /// css variable in px, e.g. `--node-gap-x`, the first value if there are more
+    pub fn px(&self, name: &str) -> f64 {
+        self.padding(name).0
+    }
+
+    /// vertical and horizontal px of a css padding variable, e.g. `--map-padding: 50px 80px`
+    fn padding(&self, name: &str) -> (f64, f64) {
+        let values: Vec<f64> = self.var(name).split_whitespace().filter_map(|v| v.trim_end_matches("px").parse().ok()).collect();
+        match values.as_slice() {
+            [] => (0.0, 0.0),
+            [v] => (*v, *v),
+            [v, h, ..] => (*v, *h),
+        }
+    }
+}
+
+/// rectangle in px, from the top left corner of the map
+#[derive(Clone, Copy, Default)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rect {
+    pub fn right(&self) -> f64 {
+        self.x + self.w
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.h
+    }
+}
+
+/// side of a branch, main nodes on the left have their children on the left
+#[derive(Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// measured content of a topic box
+pub struct Topic {
+    pub font_size:   f64,
+    pub bold:        bool,
+    pub lines:       Vec<String>, // topic wrapped at the max width of 35em
+    pub line_widths: Vec<f64>, // width of each line, without icons and link
+    pub line_height: f64,
+    pub tag_widths:  Vec<f64>, // width of each tag box
+    pub padding:     (f64, f64), // (vertical, horizontal)
+    pub border:      f64,
+}
+
+/// node with its boxes, positions are set by `place`
+pub struct Placed<'a> {
+    pub node:     &'a NodeObj,
+    pub depth:    usize, // 0: root, 1: main node
+    pub side:     Side,
+    pub branch:   String, // color of the line to this node, empty for the root
+    pub topic:    Topic,
+    pub tpc:      Rect, // topic box, with border
+    pub parent:   Rect, // topic box with the gap around it, lines of the children start here
+    pub wrapper:  Rect, // node and its children
+    pub children: Vec<Placed<'a>>, // empty if collapsed
+}
+
+impl Placed<'_> {
+    /// whether node has children that are hidden
+    pub fn collapsed(&self) -> bool {
+        self.node.expanded == Some(false) && !self.node.children.is_empty()
+    }
+
+    /// placed node by id, None if not found or hidden in a collapsed node
+    pub fn find(&self, id: &str) -> Option<&Self> {
+        if self.node.id == id {
+            return Some(self)
+        }
+        self.children.iter().find_map(|c| c.find(id))
+    }
+
+    /// all placed nodes, depth first
+    pub fn descendants(&self) -> Vec<&Self> {
+        let mut nodes = vec![self];
+        for child in &self.children {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+}
+
+/// mindmap laid out like mind-elixir, root in the middle and main branches on the left and right
+pub struct Layout<'a> {
+    pub theme:  ThemeVars,
+    pub root:   Placed<'a>,
+    pub width:  f64,
+    pub height: f64,
+}
+
+/// whether char is full width, CJK and Hangul
+fn is_wide(c: char) -> bool {
+    matches!(c as u32, 0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x20000..=0x3FFFD)
+}
+
+/// advance width of char in em, ascii uses Helvetica, emoji and CJK are about one em
+fn char_width(c: char) -> f64 {
+    match c as u32 {
+        0x20..=0x7E => f64::from(HELVETICA[c as usize - 0x20]) / 1000.0,
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F => 0.0, // combining marks, zero width joiner, variation selectors, skin tones, tag sequences
+        0x2600..=0x27BF | 0x2B00..=0x2BFF | 0x1F000..=0x1FAFF => 1.25, // emoji
+        _ if is_wide(c) => 1.0,
+        _ => 0.6,
+    }
+}
+
+/// estimated width of text in px, bold text is a little wider
+pub fn text_width(text: &str, size: f64, bold: bool) -> f64 {
+    let width: f64 = text.chars().map(char_width).sum::<f64>() * size;
+    if bold { width * 1.06 } else { width }
+}
+
+/// wrap text at `max` px like `white-space: pre-wrap`, break after spaces and around CJK chars, long words are broken anywhere
+fn wrap(text: &str, max: f64, size: f64, bold: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for hard_line in text.split('\n') {
+        let mut line = String::new();
+        let mut width = 0.0;
+        let mut break_at: Option<usize> = None; // byte index in `line` where it can break
+        for c in hard_line.chars() {
+            let w = text_width(&c.to_string(), size, bold);
+            if width + w > max && !line.is_empty() && c != ' ' {
+                let at = break_at.filter(|b| *b > 0).unwrap_or(line.len());
+                let rest = line.split_off(at);
+                lines.push(line.trim_end().to_string());
+                line = rest;
+                width = text_width(&line, size, bold);
+                break_at = None;
+            }
+            if is_wide(c) {
+                break_at = Some(line.len());
+            }
+            line.push(c);
+            width += w;
+            if c == ' ' || is_wide(c) {
+                break_at = Some(line.len());
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// measure topic box of node, `depth` 0 is the root, 1 is a main node
+fn measure_topic(node: &NodeObj, depth: usize, theme: &ThemeVars) -> (Topic, f64, f64) {
+    let style = node.style.as_ref();
+    let font_size = style.and_then(|s| s.font_size.as_deref()).and_then(font_size).map_or(if depth == 0 { 25.0 } else { 15.0 }, f64::from);
+    let bold = style.and_then(|s| s.font_weight.as_deref()).is_some_and(is_bold);
+    let padding = match depth {
+        0 => (10.0, 30.0),
+        1 => (8.0, 25.0),
+        _ => (theme.px("--topic-padding"), theme.px("--topic-padding")),
+    };
+    let border = if depth <= 1 { 2.0 } else { 0.0 };
+    let line_height = font_size * 1.2;
+    // `max-width: 35em` includes padding and border
+    let max = 35.0 * font_size - 2.0 * (padding.1 + border);
+    let lines = wrap(&node.topic, max, font_size, bold);
+    let line_widths: Vec<f64> = lines.iter().map(|l| text_width(l, font_size, bold)).collect();
+    // icons and link follow the last line
+    let mut extra = 0.0;
+    if node.hyper_link.is_some() {
+        extra += 0.3 * font_size + text_width("🔗", font_size, false);
+    }
+    if !node.icons.is_empty() {
+        extra += 5.0 + node.icons.iter().map(|i| text_width(i, font_size, false)).sum::<f64>();
+    }
+    let last = line_widths.last().copied().unwrap_or(0.0) + extra;
+    let tag_widths: Vec<f64> = node.tags.iter().map(|t| text_width(t.text(), TAG_FONT_SIZE, false) + 8.0).collect();
+    let mut width = line_widths.iter().copied().fold(last, f64::max);
+    width = width.max(tag_widths.iter().map(|w| w + 4.0).sum());
+    let mut height = line_height * lines.len() as f64;
+    if !tag_widths.is_empty() {
+        height += tag_height() + 2.0;
+    }
+    if let Some(image) = &node.image {
+        width = width.max(image.width);
+        height += image.height + 8.0;
+    }
+    let w = width + 2.0 * (padding.1 + border);
+    let h = height + 2.0 * (padding.0 + border);
+    (Topic{font_size, bold, lines, line_widths, line_height, tag_widths, padding, border}, w, h)
+}
+
+/// height of a tag box, `line-height: 1.3em` and 2px padding
+pub fn tag_height() -> f64 {
+    TAG_FONT_SIZE * 1.3 + 4.0
+}
+
+/// margins of the topic box with gap, (top, right, bottom, left)
+fn parent_margin(depth: usize, theme: &ThemeVars) -> (f64, f64, f64, f64) {
+    match depth {
+        0 => (0.0, 0.0, 0.0, 0.0),
+        1 => (10.0, 10.0, 10.0, 10.0),
+        _ => (theme.px("--node-gap-y"), 0.0, 0.0, 0.0),
+    }
+}
+
+/// measure node and its visible descendants, set the sizes of the boxes
+fn measure<'a>(node: &'a NodeObj, depth: usize, side: Side, branch: String, theme: &ThemeVars) -> Placed<'a> {
+    let (topic, w, h) = measure_topic(node, depth, theme);
+    let tpc = Rect{w, h, ..Default::default()};
+    // main topic is the parent box, other nodes have padding `6px var(--node-gap-x)`
+    let parent = if depth <= 1 { tpc } else { Rect{w: w + 2.0 * theme.px("--node-gap-x"), h: h + 12.0, ..Default::default()} };
+    let children: Vec<Placed> = if node.expanded == Some(false) || depth == 0 {
+        Vec::new() // main nodes are measured by `layout`
+    } else {
+        node.children.iter().map(|c| {
+            let branch = c.branch_color.clone().unwrap_or_else(|| branch.clone());
+            measure(c, depth + 1, side, branch, theme)
+        }).collect()
+    };
+    let (top, right, bottom, left) = parent_margin(depth, theme);
+    let outer_w = parent.w + left + right;
+    let outer_h = parent.h + top + bottom;
+    let children_w = children.iter().map(|c| c.wrapper.w).fold(0.0, f64::max);
+    let children_h: f64 = children.iter().map(|c| c.wrapper.h).sum();
+    let wrapper = Rect{w: outer_w + children_w, h: outer_h.max(children_h), ..Default::default()};
+    Placed{node, depth, side, branch, topic, tpc, parent, wrapper, children}
+}
+
+/// set positions of node and its children, the wrapper is at (`x`, `y`)
+/// the topic box is vertically centered to its children, children on the left are aligned right
+fn place(placed: &mut Placed, x: f64, y: f64, theme: &ThemeVars) {
+    placed.wrapper.x = x;
+    placed.wrapper.y = y;
+    let (top, right, bottom, left) = parent_margin(placed.depth, theme);
+    let outer_w = placed.parent.w + left + right;
+    let outer_h = placed.parent.h + top + bottom;
+    let outer_x = match placed.side {
+        Side::Right => x,
+        Side::Left => placed.wrapper.right() - outer_w,
+    };
+    let outer_y = y + (placed.wrapper.h - outer_h) / 2.0;
+    placed.parent.x = outer_x + left;
+    placed.parent.y = outer_y + top;
+    placed.tpc.x = placed.parent.x + (placed.parent.w - placed.tpc.w) / 2.0;
+    placed.tpc.y = placed.parent.y + (placed.parent.h - placed.tpc.h) / 2.0;
+    let children_h: f64 = placed.children.iter().map(|c| c.wrapper.h).sum();
+    let mut child_y = y + (placed.wrapper.h - children_h) / 2.0;
+    let side = placed.side;
+    let wrapper = placed.wrapper;
+    for child in &mut placed.children {
+        let child_x = match side {
+            Side::Right => x + outer_w,
+            Side::Left => wrapper.right() - outer_w - child.wrapper.w,
+        };
+        place(child, child_x, child_y, theme);
+        child_y += child.wrapper.h;
+    }
+}
+
+/// lay out mindmap like mind-elixir, sizes of text are estimated from font metrics
+/// main nodes go left or right by map direction, they are stacked with `--main-gap-y` between them
+/// branch colors come from the theme palette in the order of the left then the right main nodes
+pub fn layout(data: &MindMapData) -> Layout<'_> {
+    let theme = ThemeVars::new(data);
+    let root_node = &data.node_data;
+    let mut root = measure(root_node, 0, Side::Right, String::new(), &theme);
+    let sides = data.main_sides();
+    let measure_main = |i: usize, side: Side, color: usize, theme: &ThemeVars| {
+        let child = &root_node.children[i];
+        let branch = child.branch_color.clone().unwrap_or_else(|| theme.palette[color % theme.palette.len()].clone());
+        measure(child, 1, side, branch, theme)
+    };
+    let left_index: Vec<usize> = (0..sides.len()).filter(|i| sides[*i] == 0).collect();
+    let right_index: Vec<usize> = (0..sides.len()).filter(|i| sides[*i] != 0).collect();
+    let left: Vec<Placed> = left_index.iter().enumerate().map(|(n, i)| measure_main(*i, Side::Left, n, &theme)).collect();
+    let right: Vec<Placed> = right_index.iter().enumerate().map(|(n, i)| measure_main(*i, Side::Right, left.len() + n, &theme)).collect();
+    let (gap_x, gap_y) = (theme.px("--main-gap-x"), theme.px("--main-gap-y"));
+    // size of main node column, margins collapse between main nodes
+    let column = |nodes: &[Placed]| if nodes.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (
+            nodes.iter().map(|n| n.wrapper.w).fold(0.0, f64::max) + 2.0 * gap_x,
+            nodes.iter().map(|n| n.wrapper.h).sum::<f64>() + gap_y * (nodes.len() + 1) as f64,
+        )
+    };
+    let (left_w, left_h) = column(&left);
+    let (right_w, right_h) = column(&right);
+    let root_h = root.tpc.h + 90.0; // `margin: 45px 0`
+    let (pad_y, pad_x) = theme.padding("--map-padding");
+    let content_h = left_h.max(right_h).max(root_h);
+    let width = 2.0 * pad_x + left_w + root.tpc.w + right_w;
+    let height = 2.0 * pad_y + content_h;
+    let root_y = pad_y + (content_h - root.tpc.h) / 2.0;
+    place(&mut root, pad_x + left_w, root_y, &theme);
+    let mut columns = [(left, left_h, pad_x + gap_x), (right, right_h, pad_x + left_w + root.tpc.w + gap_x)];
+    for (nodes, column_h, x) in &mut columns {
+        let column_w = nodes.iter().map(|n| n.wrapper.w).fold(0.0, f64::max);
+        let mut y = pad_y + (content_h - *column_h) / 2.0 + gap_y;
+        for node in nodes.iter_mut() {
+            let node_x = match node.side {
+                Side::Right => *x,
+                Side::Left => *x + column_w - node.wrapper.w,
+            };
+            place(node, node_x, y, &theme);
+            y += node.wrapper.h + gap_y;
+        }
+    }
+    // keep the children of root in data order, summaries use their index, main nodes are shown even if the root is collapsed
+    let [(left, ..), (right, ..)] = columns;
+    let mut main: Vec<(usize, Placed)> = left_index.into_iter().zip(left).chain(right_index.into_iter().zip(right)).collect();
+    main.sort_by_key(|(i, _)| *i);
+    root.children = main.into_iter().map(|(_, p)| p).collect();
+    Layout{theme, root, width, height}
+}
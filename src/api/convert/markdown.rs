@@ -0,0 +1,325 @@
+use serde_json::Map;
+
+use crate::{
+    error::MyError,
+    model::{new_node_id, MindMapData, NodeObj, Tag},
+};
+
+use super::{node_at, push_node};
+
+/// topic in one line, line breaks become spaces
+fn one_line(s: &str) -> String {
+    s.split(['\r', '\n']).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// escape text that markdown would read as markup, `\`, `` ` ``, `[`, `]` anywhere,
+/// a leading `#`, `-`, `*`, `+`, `>` or `1.`, and trailing `#`, e.g. `# not heading` -> `\# not heading`
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '`' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    if out.starts_with(['#', '-', '*', '+', '>']) {
+        out.insert(0, '\\');
+    } else {
+        let digits = out.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 && out[digits..].starts_with(['.', ')']) {
+            out.insert(digits, '\\');
+        }
+    }
+    let closing = out.len() - out.trim_end_matches('#').len();
+    if closing > 0 && !out[..out.len() - closing].ends_with('\\') {
+        out.insert(out.len() - closing, '\\');
+    }
+    out
+}
+
+/// drop the `\` before ascii punctuation, reverse of `escape`
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(n) = chars.next_if(|n| n.is_ascii_punctuation()) {
+                out.push(n);
+                continue
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// length of the longest run of backticks
+fn backtick_run(s: &str) -> usize {
+    s.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// tag as code span, fenced by more backticks than it contains, e.g. `` `tag` `` or ``` `` a`b `` ```
+fn code_span(text: &str) -> String {
+    let fence = "`".repeat(backtick_run(text) + 1);
+    if fence.len() > 1 || (text.starts_with(' ') && text.ends_with(' ') && !text.trim().is_empty()) {
+        format!("{} {} {}", fence, text, fence)
+    } else {
+        format!("{}{}{}", fence, text, fence)
+    }
+}
+
+/// topic with hyperlink and tags, e.g. ``[topic](https://example.com) `tag1` `tag2` ``
+fn inline(node: &NodeObj) -> String {
+    let mut out = match &node.hyper_link {
+        Some(link) => format!("[{}]({})", escape(&one_line(&node.topic)), link),
+        None => escape(&one_line(&node.topic)),
+    };
+    for tag in &node.tags {
+        out.push(' ');
+        out.push_str(&code_span(tag.text()));
+    }
+    out
+}
+
+/// write node and descendants, `level` 1 is the root
+fn write_node(node: &NodeObj, level: usize, heading_depth: usize, out: &mut String) {
+    if level <= heading_depth {
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} {}\n\n", "#".repeat(level), inline(node)));
+        if let Some(note) = &node.note {
+            for line in note.lines() {
+                out.push_str(&format!("> {}\n", line));
+            }
+            out.push('\n');
+        }
+    } else {
+        let indent = "  ".repeat(level - heading_depth - 1);
+        out.push_str(&format!("{}- {}\n", indent, inline(node)));
+        if let Some(note) = &node.note {
+            for line in note.lines() {
+                out.push_str(&format!("{}  > {}\n", indent, line));
+            }
+        }
+    }
+    for child in &node.children {
+        write_node(child, level + 1, heading_depth, out);
+    }
+}
+
+/// convert mindmap to nested markdown, the root is `#` heading
+/// nodes up to `heading_depth` levels are headings (at most 6), deeper nodes are bullet lists
+/// hyperlink becomes `[topic](link)`, tags become `` `tag` `` after the topic, note becomes `> note` under the node
+pub fn to_markdown(data: &MindMapData, heading_depth: usize) -> String {
+    let mut out = String::new();
+    write_node(&data.node_data, 1, heading_depth.clamp(1, 6), &mut out);
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+    out
+}
+
+/// byte index of the first `pat` not escaped by `\`
+fn find_unescaped(s: &str, pat: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if s[i..].starts_with(pat) {
+            return Some(i)
+        }
+    }
+    None
+}
+
+/// parse topic written by `inline`, return (topic, hyperlink, tags)
+fn parse_inline(text: &str) -> (String, Option<String>, Vec<Tag>) {
+    let mut text = text.trim().to_string();
+    // trailing `` `tag` ``, the fence may be several backticks
+    let mut tags = Vec::new();
+    while text.ends_with('`') {
+        let n = text.len() - text.trim_end_matches('`').len();
+        let body = &text[..text.len() - n];
+        let open = format!(" {}", "`".repeat(n));
+        match body.rfind(&open) {
+            Some(i) if i + open.len() < body.len() && !body[i + open.len()..].starts_with('`') && backtick_run(&body[i + open.len()..]) < n => {
+                let mut tag = &body[i + open.len()..];
+                if tag.len() > 2 && tag.starts_with(' ') && tag.ends_with(' ') && !tag.trim().is_empty() {
+                    tag = &tag[1..tag.len() - 1];
+                }
+                tags.push(Tag::Text(tag.to_string()));
+                text = body[..i].trim_end().to_string();
+            },
+            _ => break,
+        }
+    }
+    tags.reverse();
+    // whole topic is `[topic](link)`
+    if text.starts_with('[') && text.ends_with(')') {
+        if let Some(i) = find_unescaped(&text, "](") {
+            let link = text[i + 2..text.len() - 1].to_string();
+            let topic = unescape(&text[1..i]);
+            return (topic, Some(link), tags)
+        }
+    }
+    (unescape(&text), None, tags)
+}
+
+/// list item marker, `-`, `*`, `+`, `1.` or `1)`, return the text after it
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.strip_prefix([' ', '\t'])
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(rest) = line[digits..].strip_prefix(['.', ')']) {
+            return rest.strip_prefix([' ', '\t'])
+        }
+    }
+    None
+}
+
+/// heading `# text` to `###### text`, return (level, text)
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) {
+        if let Some(text) = line[level..].strip_prefix([' ', '\t']) {
+            // closing `#`s only count after a space, so `C#` keeps its `#`
+            let text = text.trim();
+            let open = text.trim_end_matches('#');
+            if open.is_empty() || open.ends_with([' ', '\t']) {
+                return Some((level, open.trim()))
+            }
+            return Some((level, text))
+        }
+    }
+    None
+}
+
+/// append one line to node note
+fn push_note(node: &mut NodeObj, line: &str) {
+    match &mut node.note {
+        Some(n) => {
+            n.push('\n');
+            n.push_str(line);
+        },
+        None => node.note = Some(line.to_string()),
+    }
+}
+
+/// parse headings and nested lists into a mindmap, every node gets a new id
+/// a single top-level heading or item becomes the root, otherwise a root is added with topic `root_topic`
+/// `> quote` and other text lines become the note of the node above them
+pub fn from_markdown(text: &str, root_topic: &str) -> Result<MindMapData, MyError> {
+    let mut forest: Vec<NodeObj> = Vec::new();
+    let mut stack: Vec<(usize, Vec<usize>)> = Vec::new(); // (rank, path in forest), rank of heading is its level, rank of list item is 10 + indent
+    let mut preamble: Vec<String> = Vec::new(); // text before the first node
+    let mut in_fence = false;
+    for line in text.lines() {
+        let expanded = line.replace('\t', "    ");
+        let trimmed = expanded.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let item = if in_fence { None } else {
+            heading(trimmed)
+                .or_else(|| list_item(trimmed).map(|t| (10 + expanded.len() - trimmed.len(), t)))
+        };
+        match item {
+            Some((rank, t)) => {
+                let (topic, hyper_link, tags) = parse_inline(t);
+                let node = NodeObj{id: new_node_id(), topic, hyper_link, tags, ..Default::default()};
+                push_node(&mut forest, &mut stack, rank, node);
+            },
+            None => {
+                let note = trimmed.strip_prefix('>').map(|q| q.strip_prefix(' ').unwrap_or(q)).unwrap_or(trimmed);
+                if trimmed.is_empty() && !in_fence {
+                    continue
+                }
+                match stack.last() {
+                    Some((_, path)) => push_note(node_at(&mut forest, path), note),
+                    None => preamble.push(note.to_string()),
+                }
+            },
+        }
+    }
+    let mut root = match forest.len() {
+        0 => return Err(MyError::ValidationError{reason: "no heading or list item found in markdown".to_string()}),
+        1 => forest.pop().unwrap(),
+        _ => NodeObj{id: new_node_id(), topic: root_topic.to_string(), children: forest, ..Default::default()},
+    };
+    for line in preamble.into_iter().rev() {
+        match &mut root.note {
+            Some(n) => *n = format!("{}\n{}", line, n),
+            None => root.note = Some(line),
+        }
+    }
+    let data = MindMapData{node_data: root, arrows: Vec::new(), summaries: Vec::new(), direction: None, theme: None, extra: Map::new()};
+    data.validate()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (topic, hyperlink, tags) of node and descendants in depth-first order
+    fn flatten(node: &NodeObj, out: &mut Vec<(String, Option<String>, Vec<String>)>) {
+        out.push((node.topic.clone(), node.hyper_link.clone(), node.tags.iter().map(|t| t.text().to_string()).collect()));
+        for child in &node.children {
+            flatten(child, out);
+        }
+    }
+
+    #[test]
+    fn round_trip_escapes_markup() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"# not a heading","children":[
+            {"id":"a","topic":"- dash","tags":["#tag","a`b","`"],"children":[
+                {"id":"a1","topic":"* star"},
+                {"id":"a2","topic":"1. first","hyperLink":"https://example.com/(1)"},
+                {"id":"a3","topic":"C#"},
+                {"id":"a4","topic":"[not](link)"}
+            ]},
+            {"id":"b","topic":"code `x` and back\\slash \\*","tags":["plain"],"children":[
+                {"id":"b1","topic":"[brackets]","hyperLink":"https://example.com"},
+                {"id":"b2","topic":"> quote ###"}
+            ]}
+        ]}}"##).unwrap();
+        for depth in [1, 2, 6] {
+            let back = from_markdown(&to_markdown(&data, depth), "root").unwrap();
+            let (mut expected, mut actual) = (Vec::new(), Vec::new());
+            flatten(&data.node_data, &mut expected);
+            flatten(&back.node_data, &mut actual);
+            assert_eq!(actual, expected, "heading depth {}", depth);
+        }
+        let md = to_markdown(&data, 1);
+        assert!(md.starts_with("# \\# not a heading\n"));
+        assert!(md.contains("- \\- dash `#tag` `` a`b `` `` ` ``\n"));
+        assert!(md.contains("  - [1\\. first](https://example.com/(1))\n  - C\\#\n"));
+    }
+
+    #[test]
+    fn import_headings_lists_and_notes() {
+        let md = "intro line\n\n# Plan #\n\n> why\n\n## Step\\_one\n\n- item `tag`\n  * [child](https://example.com)\n    note text\n1) other\n\n```\n# in fence\n```\n";
+        let data = from_markdown(md, "fallback").unwrap();
+        let root = &data.node_data;
+        assert_eq!(root.topic, "Plan");
+        assert_eq!(root.note.as_deref(), Some("intro line\nwhy"));
+        let step = &root.children[0];
+        assert_eq!(step.topic, "Step_one");
+        let topics: Vec<&str> = step.children.iter().map(|c| c.topic.as_str()).collect();
+        assert_eq!(topics, ["item", "other"]);
+        assert_eq!(step.children[0].tags[0].text(), "tag");
+        let child = &step.children[0].children[0];
+        assert_eq!((child.topic.as_str(), child.hyper_link.as_deref()), ("child", Some("https://example.com")));
+        assert_eq!(child.note.as_deref(), Some("note text"));
+        assert_eq!(step.children[1].note.as_deref(), Some("```\n# in fence\n```"));
+        let several = from_markdown("- a\n- b\n", "fallback").unwrap();
+        assert_eq!(several.node_data.topic, "fallback");
+        assert_eq!(several.node_data.children.len(), 2);
+        assert!(from_markdown("just text\n", "fallback").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use serde_json::Map;
+
+use crate::{
+    error::MyError,
+    model::{new_node_id, MindMapData, NodeObj},
+};
+
+use super::{node_at, push_node};
+
+/// Font Awesome icons of Mermaid `::icon()` and the emoji shown by mind-elixir
+const ICONS: [(&str, &str); 24] = [
+    ("fa fa-lightbulb", "💡"),
+    ("fa fa-check", "✅"),
+    ("fa fa-times", "❌"),
+    ("fa fa-star", "⭐"),
+    ("fa fa-flag", "🚩"),
+    ("fa fa-question", "❓"),
+    ("fa fa-exclamation", "❗"),
+    ("fa fa-exclamation-triangle", "⚠️"),
+    ("fa fa-info", "ℹ️"),
+    ("fa fa-calendar", "📅"),
+    ("fa fa-clock", "⏰"),
+    ("fa fa-bell", "🔔"),
+    ("fa fa-rocket", "🚀"),
+    ("fa fa-key", "🔑"),
+    ("fa fa-paperclip", "📎"),
+    ("fa fa-envelope", "✉️"),
+    ("fa fa-pencil", "✏️"),
+    ("fa fa-home", "🏠"),
+    ("fa fa-book", "📖"),
+    ("fa fa-list", "📋"),
+    ("fa fa-smile", "😊"),
+    ("fa fa-bug", "🐛"),
+    ("fa fa-heart", "❤️"),
+    ("fa fa-user", "👤"),
+];
+
+/// node shapes, (open, close), longer delimiters first
+const SHAPES: [(&str, &str); 6] = [
+    ("((", "))"),
+    ("))", "(("),
+    ("{{", "}}"),
+    ("[", "]"),
+    ("(", ")"),
+    (")", "("),
+];
+
+/// Mermaid icon class of the first icon that has one, Mermaid shows at most one icon for each node
+fn icon_class(icons: &[String]) -> Option<String> {
+    icons.iter().find_map(|icon| match ICONS.iter().find(|(_, e)| e == icon) {
+        Some((class, _)) => Some(class.to_string()),
+        None => (icon.starts_with("fa ") || icon.starts_with("mdi ")).then(|| icon.clone()),
+    })
+}
+
+/// node text, quoted if it contains shape delimiters, `"` becomes `#quot;` and line breaks become `<br/>`
+fn node_text(topic: &str) -> String {
+    let text = topic.replace('"', "#quot;").replace('\n', "<br/>");
+    if text.is_empty() || text.contains(['(', ')', '[', ']', '{', '}']) || text.trim() != text {
+        format!("\"{}\"", text)
+    } else {
+        text
+    }
+}
+
+/// node id with only `[A-Za-z0-9_]`, other characters become `_`, ids already `used` get a `_2`, `_3` suffix
+fn mermaid_id(id: &str, used: &mut HashSet<String>) -> String {
+    let mut base: String = id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if base.is_empty() {
+        base.push('n');
+    }
+    let mut id = base.clone();
+    let mut n = 1;
+    while !used.insert(id.clone()) {
+        n += 1;
+        id = format!("{}_{}", base, n);
+    }
+    id
+}
+
+/// write node and descendants, `depth` 0 is the root
+fn write_node(node: &NodeObj, depth: usize, used: &mut HashSet<String>, out: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    let (open, close) = if depth == 0 { ("((", "))") } else { ("[", "]") };
+    out.push_str(&format!("{}{}{}{}{}\n", indent, mermaid_id(&node.id, used), open, node_text(&node.topic), close));
+    if let Some(class) = icon_class(&node.icons) {
+        out.push_str(&format!("{}  ::icon({})\n", indent, class));
+    }
+    for child in &node.children {
+        write_node(child, depth + 1, used, out);
+    }
+}
+
+/// convert mindmap to Mermaid `mindmap`, the root is a circle, other nodes are squares with their node id
+/// characters of node ids outside `[A-Za-z0-9_]` become `_`
+/// Mermaid places nodes by itself, so sides are not kept, tags, notes, links, arrows and summaries are dropped
+pub fn to_mermaid(data: &MindMapData) -> String {
+    let mut out = String::from("mindmap\n");
+    write_node(&data.node_data, 0, &mut HashSet::new(), &mut out);
+    out
+}
+
+/// topic of node text, `#quot;` becomes `"` and `<br/>` becomes line break
+fn topic(text: &str) -> String {
+    text.replace("#quot;", "\"").replace("<br/>", "\n").replace("<br>", "\n")
+}
+
+/// parse one node line, `id[text]`, `id((text))` and other shapes, or only text, return (id, topic)
+fn parse_node(line: &str) -> (Option<String>, String) {
+    if let Some(i) = line.find(['(', ')', '[', '{']) {
+        let (id, shape) = line.split_at(i);
+        for (open, close) in SHAPES {
+            if let Some(text) = shape.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+                let text = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text);
+                let text = text.strip_prefix('`').and_then(|t| t.strip_suffix('`')).unwrap_or(text); // markdown string
+                return (Some(id.trim().to_string()).filter(|i| !i.is_empty()), topic(text))
+            }
+        }
+    }
+    (None, topic(line))
+}
+
+/// parse Mermaid `mindmap`, the first ```` ```mermaid ```` block if present, node ids are kept from `id[text]`
+/// nesting is given by indentation, `::icon()` adds an icon to the node above it
+/// a single top-level node becomes the root, otherwise a root is added with topic `root_topic`
+pub fn from_mermaid(text: &str, root_topic: &str) -> Result<MindMapData, MyError> {
+    let mut lines: Vec<&str> = text.lines().collect();
+    if let Some(start) = lines.iter().position(|l| l.trim().starts_with("```mermaid")) {
+        let end = lines[start + 1..].iter().position(|l| l.trim().starts_with("```")).map_or(lines.len(), |e| start + 1 + e);
+        lines = lines[start + 1..end].to_vec();
+    }
+    let mut forest: Vec<NodeObj> = Vec::new();
+    let mut stack: Vec<(usize, Vec<usize>)> = Vec::new(); // (indent, path in forest)
+    let mut last: Option<Vec<usize>> = None;
+    let mut started = false; // after `mindmap`
+    let mut front_matter = false;
+    for line in lines {
+        let expanded = line.replace('\t', "    ");
+        let trimmed = expanded.trim();
+        if !started {
+            if trimmed == "---" {
+                front_matter = !front_matter;
+            }
+            started = !front_matter && trimmed == "mindmap";
+            continue
+        }
+        if trimmed.is_empty() || trimmed.starts_with("%%") || trimmed.starts_with(":::") {
+            continue
+        }
+        if let Some(class) = trimmed.strip_prefix("::icon(").and_then(|c| c.strip_suffix(')')) {
+            if let Some(path) = &last {
+                let icon = match ICONS.iter().find(|(c, _)| *c == class.trim()) {
+                    Some((_, emoji)) => emoji.to_string(),
+                    None => class.trim().to_string(),
+                };
+                node_at(&mut forest, path).icons.push(icon);
+            }
+            continue
+        }
+        let (id, topic) = parse_node(trimmed);
+        let node = NodeObj{id: id.unwrap_or_else(new_node_id), topic, ..Default::default()};
+        last = Some(push_node(&mut forest, &mut stack, expanded.len() - expanded.trim_start().len(), node));
+    }
+    let root = match forest.len() {
+        0 => return Err(MyError::ValidationError{reason: "no node found in mermaid mindmap".to_string()}),
+        1 => forest.pop().unwrap(),
+        _ => NodeObj{id: new_node_id(), topic: root_topic.to_string(), children: forest, ..Default::default()},
+    };
+    let mut data = MindMapData{node_data: root, arrows: Vec::new(), summaries: Vec::new(), direction: None, theme: None, extra: Map::new()};
+    data.ensure_unique_ids();
+    data.validate()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_ids_topics_and_icons() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"root","children":[
+            {"id":"a1","topic":"with (shape) \"quotes\"","icons":["💡"],"children":[{"id":"b2","topic":"two\nlines"}]},
+            {"id":"c3","topic":" padded "}
+        ]}}"##).unwrap();
+        let back = from_mermaid(&format!("# doc\n```mermaid\n{}```\n", to_mermaid(&data)), "fallback").unwrap();
+        assert_eq!(serde_json::to_value(&back.node_data).unwrap(), serde_json::to_value(&data.node_data).unwrap());
+    }
+
+    #[test]
+    fn ids_are_sanitised() {
+        let data = MindMapData::parse(r#"{"nodeData":{"id":"me-root","topic":"root","children":[
+            {"id":"a b)[x]","topic":"first"},
+            {"id":"a_b__x_","topic":"second"},
+            {"id":"日本","topic":"third"}
+        ]}}"#).unwrap();
+        let mermaid = to_mermaid(&data);
+        assert!(mermaid.contains("  me_root((root))\n"));
+        assert!(mermaid.contains("    a_b__x_[first]\n    a_b__x__2[second]\n    __[third]\n"));
+        let back = from_mermaid(&mermaid, "fallback").unwrap();
+        let topics: Vec<&str> = back.node_data.children.iter().map(|c| c.topic.as_str()).collect();
+        assert_eq!(topics, ["first", "second", "third"]);
+    }
+}
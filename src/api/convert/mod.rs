@@ -0,0 +1,320 @@
+use std::fmt;
+use std::fs::{read, write};
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::{
+    error::MyError,
+    map_id::MapId,
+    model::{MindMapData, NodeObj},
+    parse_paras::PARAS,
+    viewer_page,
+    MindMap,
+    DATA,
+};
+
+pub mod dot;
+pub mod freemind;
+pub mod layout;
+pub mod markdown;
+pub mod mermaid;
+pub mod opml;
+pub mod plantuml;
+pub mod render;
+pub mod svg;
+pub mod xmind;
+
+/// export and import format, e.g. `/download/:uuid?format=md`
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Format {
+    Html, // editor page
+    Viewer, // read-only page without the server controls, export only
+    Md, // markdown outline
+    Opml, // OPML 2.0 outline
+    Mm, // FreeMind and Freeplane
+    Xmind, // XMind 2020 and later workbook
+    Plantuml, // PlantUML `@startmindmap`
+    Mermaid, // Mermaid `mindmap`
+    Dot, // Graphviz DOT, export only
+    Svg, // image laid out like mind-elixir, export only
+    Png, // svg rendered by the server, export only
+    Pdf, // svg on A4 pages, export only
+}
+
+impl Format {
+    /// file extension, without `.`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Html | Format::Viewer => "html",
+            Format::Md => "md",
+            Format::Opml => "opml",
+            Format::Mm => "mm",
+            Format::Xmind => "xmind",
+            Format::Plantuml => "puml",
+            Format::Mermaid => "mmd",
+            Format::Dot => "gv",
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Pdf => "pdf",
+        }
+    }
+
+    /// value of `Content-Type`
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Html | Format::Viewer => "text/html; charset=utf-8",
+            Format::Md => "text/markdown; charset=utf-8",
+            Format::Opml => "text/x-opml; charset=utf-8",
+            Format::Mm => "application/x-freemind; charset=utf-8",
+            Format::Xmind => "application/vnd.xmind.workbook",
+            Format::Plantuml | Format::Mermaid => "text/plain; charset=utf-8",
+            Format::Dot => "text/vnd.graphviz; charset=utf-8",
+            Format::Svg => "image/svg+xml",
+            Format::Png => "image/png",
+            Format::Pdf => "application/pdf",
+        }
+    }
+
+    /// image of the laid out mindmap, can be rendered by `-r` and `/render/:uuid.svg`
+    pub fn is_image(&self) -> bool {
+        matches!(self, Format::Svg | Format::Png | Format::Pdf)
+    }
+
+    /// format by file extension, e.g. `notes.md`
+    pub fn from_path(path: &Path) -> Result<Self, MyError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) => e.parse(),
+            None => Err(MyError::ParaError{para: format!("can not get format from {}, use -f", path.display())}),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "html" => Ok(Format::Html),
+            "viewer" => Ok(Format::Viewer),
+            "md" | "markdown" => Ok(Format::Md),
+            "opml" => Ok(Format::Opml),
+            "mm" | "freemind" | "freeplane" => Ok(Format::Mm),
+            "xmind" => Ok(Format::Xmind),
+            "plantuml" | "puml" => Ok(Format::Plantuml),
+            "mermaid" | "mmd" => Ok(Format::Mermaid),
+            "dot" | "gv" | "graphviz" => Ok(Format::Dot),
+            "svg" => Ok(Format::Svg),
+            "png" => Ok(Format::Png),
+            "pdf" => Ok(Format::Pdf),
+            _ => Err(MyError::ParaError{para: format!("unsupported format {:?}, support: html, viewer, md, opml, mm, xmind, plantuml, mermaid, dot, svg, png, pdf", s)}),
+        }
+    }
+}
+
+impl TryFrom<String> for Format {
+    type Error = MyError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// export options, only used by some formats
+pub struct ExportOptions {
+    pub heading_depth: usize, // markdown, levels written as headings, deeper levels are bullet lists, default: 2
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions{heading_depth: 2}
+    }
+}
+
+/// load uuid mindmap and parse it, without validation so old mindmap can still be exported, return (mindmap, label)
+fn load_data(data: &mut MindMap, uuid: &MapId) -> Result<(MindMapData, Option<String>), MyError> {
+    let (content, label) = match data.load_mindmap(uuid) {
+        Some(c) => c,
+        None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    };
+    match serde_json::from_str(&content) {
+        Ok(d) => Ok((d, label)),
+        Err(e) => Err(MyError::ValidationError{reason: format!("invalid mindmap data: {}", e)}),
+    }
+}
+
+/// node at `path` of child indexes in `forest`
+fn node_at<'a>(forest: &'a mut [NodeObj], path: &[usize]) -> &'a mut NodeObj {
+    let mut node = &mut forest[path[0]];
+    for i in &path[1..] {
+        node = &mut node.children[*i];
+    }
+    node
+}
+
+/// add `node` to `forest` as the last child of the nearest node in `stack` with lower rank, or as a new tree
+/// `stack` is (rank, path in forest) of the nodes from the top-level node to the last added node, return the path of `node`
+fn push_node(forest: &mut Vec<NodeObj>, stack: &mut Vec<(usize, Vec<usize>)>, rank: usize, node: NodeObj) -> Vec<usize> {
+    while stack.last().is_some_and(|(r, _)| *r >= rank) {
+        stack.pop();
+    }
+    let path = match stack.last() {
+        Some((_, parent_path)) => {
+            let parent = node_at(forest, parent_path);
+            parent.children.push(node);
+            let mut path = parent_path.clone();
+            path.push(parent.children.len() - 1);
+            path
+        },
+        None => {
+            forest.push(node);
+            vec![forest.len() - 1]
+        },
+    };
+    stack.push((rank, path.clone()));
+    path
+}
+
+/// number of a css font size, e.g. `14px` -> `14`, XMind `14pt` is also accepted
+fn font_size(size: &str) -> Option<u32> {
+    size.trim().trim_end_matches("px").trim_end_matches("pt").trim().parse::<f64>().ok().map(|s| s.round() as u32)
+}
+
+/// whether css font weight is bold
+fn is_bold(weight: &str) -> bool {
+    matches!(weight, "bold" | "bolder") || weight.parse::<u32>().is_ok_and(|w| w >= 600)
+}
+
+/// import content of text format must be utf-8
+fn utf8(content: &[u8], format: Format) -> Result<&str, MyError> {
+    match std::str::from_utf8(content) {
+        Ok(t) => Ok(t),
+        Err(e) => Err(MyError::ValidationError{reason: format!("{} is not utf-8: {}", format, e)}),
+    }
+}
+
+/// export uuid mindmap to the given format, load it from local if not loaded
+pub fn export(data: &mut MindMap, uuid: &MapId, format: Format, options: &ExportOptions) -> Result<Vec<u8>, MyError> {
+    match format {
+        Format::Html => match data.html_content(uuid) {
+            Some((html, _)) => Ok(html.into_bytes()),
+            None => Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+        },
+        _ => {
+            let (mindmap, label) = load_data(data, uuid)?;
+            convert(&mindmap, label, format, options)
+        },
+    }
+}
+
+/// convert mindmap to `format`, `label` is the title of the document if the format has one, the root topic if None
+/// html is the editor page of the server, so it is only exported by `export`
+fn convert(mindmap: &MindMapData, label: Option<String>, format: Format, options: &ExportOptions) -> Result<Vec<u8>, MyError> {
+    let title = label.unwrap_or_else(|| mindmap.node_data.topic.clone());
+    match format {
+        Format::Html => Err(MyError::ParaError{para: "html is exported from the loaded mindmap".to_string()}),
+        Format::Viewer => Ok(viewer_page(&mindmap.to_json(), &title).into_bytes()),
+        Format::Md => Ok(markdown::to_markdown(mindmap, options.heading_depth).into_bytes()),
+        Format::Opml => Ok(opml::to_opml(mindmap, &title).into_bytes()),
+        Format::Mm => Ok(freemind::to_freemind(mindmap).into_bytes()),
+        Format::Xmind => xmind::to_xmind(mindmap, &title),
+        Format::Plantuml => Ok(plantuml::to_plantuml(mindmap).into_bytes()),
+        Format::Mermaid => Ok(mermaid::to_mermaid(mindmap).into_bytes()),
+        Format::Dot => Ok(dot::to_dot(mindmap, &title).into_bytes()),
+        Format::Svg => Ok(svg::to_svg(mindmap).into_bytes()),
+        Format::Png => render::to_png(mindmap),
+        Format::Pdf => render::to_pdf(mindmap, &title),
+    }
+}
+
+/// export uuid mindmap of the server as `format` except html, return (file content, revision)
+/// the data lock is only held to copy the mindmap, conversion runs in a blocking thread so rendering png or pdf does not hold up other requests
+pub async fn export_unlocked(uuid: &MapId, format: Format, options: ExportOptions) -> Result<(Vec<u8>, u64), MyError> {
+    let (mindmap, label, revision) = {
+        let mut data = DATA.write().unwrap();
+        let (mindmap, label) = load_data(&mut data, uuid)?;
+        (mindmap, label, data.revision(uuid).unwrap_or(1))
+    };
+    match tokio::task::spawn_blocking(move || convert(&mindmap, label, format, &options)).await {
+        Ok(content) => Ok((content?, revision)),
+        Err(e) => Err(MyError::RenderError{reason: e.to_string()}),
+    }
+}
+
+/// import content as new mindmaps with new uuids, return the uuids, one for each XMind sheet, one for other formats
+/// `label` is also the root topic if the content has more than one top-level node
+/// each XMind sheet is labeled with its title, unless `label` is given and there is only one sheet
+pub fn import(data: &mut MindMap, content: &[u8], format: Format, label: Option<String>) -> Result<Vec<MapId>, MyError> {
+    let root_topic = label.as_deref().unwrap_or("root");
+    let mindmaps = match format {
+        Format::Md => vec![(label.clone(), markdown::from_markdown(utf8(content, format)?, root_topic)?)],
+        Format::Opml => vec![(label.clone(), opml::from_opml(utf8(content, format)?, root_topic)?)],
+        Format::Mm => vec![(label.clone(), freemind::from_freemind(utf8(content, format)?)?)],
+        Format::Plantuml => vec![(label.clone(), plantuml::from_plantuml(utf8(content, format)?, root_topic)?)],
+        Format::Mermaid => vec![(label.clone(), mermaid::from_mermaid(utf8(content, format)?, root_topic)?)],
+        Format::Xmind => {
+            let sheets = xmind::from_xmind(content)?;
+            let single = sheets.len() == 1;
+            sheets.into_iter().map(|(title, mindmap)| match &label {
+                Some(l) if single => (Some(l.clone()), mindmap),
+                _ => (Some(title).filter(|t| !t.is_empty()), mindmap),
+            }).collect()
+        },
+        Format::Html | Format::Viewer | Format::Dot | Format::Svg | Format::Png | Format::Pdf => return Err(MyError::ParaError{para: format!("can not import {} format", format)}),
+    };
+    Ok(mindmaps.into_iter().map(|(label, mindmap)| {
+        let uuid = MapId::new();
+        data.update_loaded_mindmap(uuid.clone(), mindmap.to_json(), label);
+        uuid
+    }).collect())
+}
+
+/// export mode of command line, `-e <uuid> -f <format> --export-to <file>`, print to stdout if no `--export-to`
+pub fn run_export(uuid: &MapId, format: Format) -> Result<(), MyError> {
+    let options = ExportOptions{heading_depth: PARAS.heading_depth};
+    let content = export(&mut DATA.write().unwrap(), uuid, format, &options)?;
+    match &PARAS.export_to {
+        Some(f) => if let Err(e) = write(f, &content) {
+            return Err(MyError::WriteFileError{file: f.display().to_string(), error: e})
+        },
+        None => stdout().write_all(&content)?,
+    }
+    Ok(())
+}
+
+/// render mode of command line, `-r <uuid> -f <format> --export-to <file>`, the same as export mode with svg, png or pdf format, default: svg
+pub fn run_render(uuid: &MapId) -> Result<(), MyError> {
+    let format = PARAS.format.unwrap_or(Format::Svg);
+    if !format.is_image() {
+        return Err(MyError::ParaError{para: format!("can not render {} format, support: svg, png, pdf", format)})
+    }
+    run_export(uuid, format)
+}
+
+/// import mode of command line, `-i <file> -f <format>`, format is from the file extension if no `-f`
+/// save the new mindmap to output path and print its uuid
+pub fn run_import(path: &Path) -> Result<(), MyError> {
+    let format = match PARAS.format {
+        Some(f) => f,
+        None => Format::from_path(path)?,
+    };
+    let content = match read(path) {
+        Ok(c) => c,
+        Err(_) => return Err(MyError::FileNotExistError{file: path.display().to_string()}),
+    };
+    let mut data = DATA.write().unwrap();
+    for uuid in import(&mut data, &content, format, PARAS.label.clone())? {
+        data.save_loaded_mindmap(&uuid)?;
+        println!("{}", uuid);
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use roxmltree::{Document, Node};
+use serde_json::Map;
+
+use crate::{
+    error::MyError,
+    escape::escape_xml,
+    model::{MindMapData, NodeObj, NodeStyle, Tag},
+};
+
+/// write node and descendants as `<outline>`, `depth` is the indent level
+/// topic is `text`, hyperlink is `url` with `type="link"`, note is `_note`
+/// other metadata are custom attributes: `_id`, `_tags`, `_icons` and `_style` (json), `_expanded`
+fn write_outline(node: &NodeObj, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}<outline text=\"{}\" _id=\"{}\"", indent, escape_xml(&node.topic), escape_xml(&node.id)));
+    if let Some(link) = &node.hyper_link {
+        out.push_str(&format!(" type=\"link\" url=\"{}\"", escape_xml(link)));
+    }
+    if let Some(note) = &node.note {
+        out.push_str(&format!(" _note=\"{}\"", escape_xml(note)));
+    }
+    if !node.tags.is_empty() {
+        out.push_str(&format!(" _tags=\"{}\"", escape_xml(&serde_json::to_string(&node.tags).unwrap())));
+    }
+    if !node.icons.is_empty() {
+        out.push_str(&format!(" _icons=\"{}\"", escape_xml(&serde_json::to_string(&node.icons).unwrap())));
+    }
+    if let Some(style) = &node.style {
+        out.push_str(&format!(" _style=\"{}\"", escape_xml(&serde_json::to_string(style).unwrap())));
+    }
+    if let Some(expanded) = node.expanded {
+        out.push_str(&format!(" _expanded=\"{}\"", expanded));
+    }
+    if node.children.is_empty() {
+        out.push_str("/>\n");
+    } else {
+        out.push_str(">\n");
+        for child in &node.children {
+            write_outline(child, depth + 1, out);
+        }
+        out.push_str(&format!("{}</outline>\n", indent));
+    }
+}
+
+/// convert mindmap to OPML 2.0, the root is the only top-level outline, `title` is the head title
+pub fn to_opml(data: &MindMapData, title: &str) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str(&format!("  <head>\n    <title>{}</title>\n  </head>\n  <body>\n", escape_xml(title)));
+    write_outline(&data.node_data, 2, &mut out);
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+/// read one `<outline>` and its children, unknown custom attributes are ignored
+/// tags can also be comma separated text, e.g. `_tags="a,b"`
+fn read_outline(outline: Node) -> NodeObj {
+    let mut node = NodeObj{
+        id:    outline.attribute("_id").unwrap_or_default().to_string(),
+        topic: outline.attribute("text").or_else(|| outline.attribute("title")).unwrap_or_default().to_string(),
+        ..Default::default()
+    };
+    node.hyper_link = outline.attribute("url").or_else(|| outline.attribute("htmlUrl")).map(|u| u.to_string());
+    node.note = outline.attribute("_note").filter(|n| !n.is_empty()).map(|n| n.to_string());
+    if let Some(tags) = outline.attribute("_tags") {
+        node.tags = serde_json::from_str(tags).unwrap_or_else(|_| {
+            tags.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()).map(|t| Tag::Text(t.to_string())).collect()
+        });
+    }
+    if let Some(icons) = outline.attribute("_icons") {
+        node.icons = serde_json::from_str(icons).unwrap_or_default();
+    }
+    if let Some(style) = outline.attribute("_style") {
+        node.style = serde_json::from_str::<NodeStyle>(style).ok();
+    }
+    node.expanded = outline.attribute("_expanded").and_then(|e| e.parse().ok());
+    node.children = outline.children().filter(|c| c.has_tag_name("outline")).map(read_outline).collect();
+    node
+}
+
+/// parse OPML, a single top-level outline becomes the root, otherwise a root is added with the head title or `root_topic`
+/// node ids are kept from `_id`, missing or duplicate ids get new ones
+pub fn from_opml(text: &str, root_topic: &str) -> Result<MindMapData, MyError> {
+    let doc = match Document::parse(text) {
+        Ok(d) => d,
+        Err(e) => return Err(MyError::ValidationError{reason: format!("invalid opml: {}", e)}),
+    };
+    let opml = doc.root_element();
+    if !opml.has_tag_name("opml") {
+        return Err(MyError::ValidationError{reason: format!("invalid opml: root element is <{}>, not <opml>", opml.tag_name().name())})
+    }
+    let title = opml.children()
+        .find(|c| c.has_tag_name("head"))
+        .and_then(|h| h.children().find(|c| c.has_tag_name("title")))
+        .and_then(|t| t.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    let body = match opml.children().find(|c| c.has_tag_name("body")) {
+        Some(b) => b,
+        None => return Err(MyError::ValidationError{reason: "invalid opml: missing <body>".to_string()}),
+    };
+    let mut forest: Vec<NodeObj> = body.children().filter(|c| c.has_tag_name("outline")).map(read_outline).collect();
+    let root = match forest.len() {
+        0 => return Err(MyError::ValidationError{reason: "no outline found in opml".to_string()}),
+        1 => forest.pop().unwrap(),
+        _ => NodeObj{topic: title.unwrap_or_else(|| root_topic.to_string()), children: forest, ..Default::default()},
+    };
+    let mut data = MindMapData{node_data: root, arrows: Vec::new(), summaries: Vec::new(), direction: None, theme: None, extra: Map::new()};
+    data.ensure_unique_ids();
+    data.validate()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_keeps_node_data() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"根 <root> & \"quotes\"","expanded":true,"children":[
+            {"id":"a1","topic":"tagged","tags":["plain",{"text":"object","className":"red","style":{"color":"#f00"}}],"icons":["😀","✅"]},
+            {"id":"b2","topic":"styled","style":{"fontSize":"24px","color":"#e87a90","background":"#fff","fontWeight":"bold","textDecoration":"underline"},"children":[
+                {"id":"c3","topic":"linked","hyperLink":"https://example.com/?a=1&b='2'","note":"first line\nsecond <line> & \"end\""}
+            ]}
+        ]}}"##).unwrap();
+        let back = from_opml(&to_opml(&data, "title"), "root").unwrap();
+        assert_eq!(serde_json::to_value(&back.node_data).unwrap(), serde_json::to_value(&data.node_data).unwrap());
+        let link = back.node_data.find("c3").unwrap();
+        assert_eq!(link.hyper_link.as_deref(), Some("https://example.com/?a=1&b='2'"));
+        assert_eq!(link.note.as_deref(), Some("first line\nsecond <line> & \"end\""));
+    }
+
+    #[test]
+    fn several_outlines_get_title_root() {
+        let opml = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title> Reading list </title></head>
+  <body>
+    <outline text="first" url="https://example.com/1"/>
+    <outline text="second"><outline text="child" _tags="a, b"/></outline>
+  </body>
+</opml>"#;
+        let data = from_opml(opml, "fallback").unwrap();
+        assert_eq!(data.node_data.topic, "Reading list");
+        assert!(!data.node_data.id.is_empty());
+        let topics: Vec<&str> = data.node_data.children.iter().map(|c| c.topic.as_str()).collect();
+        assert_eq!(topics, ["first", "second"]);
+        assert_eq!(data.node_data.children[0].hyper_link.as_deref(), Some("https://example.com/1"));
+        let tags: Vec<&str> = data.node_data.children[1].children[0].tags.iter().map(|t| t.text()).collect();
+        assert_eq!(tags, ["a", "b"]);
+        let untitled = from_opml(&opml.replace("<head><title> Reading list </title></head>", ""), "fallback").unwrap();
+        assert_eq!(untitled.node_data.topic, "fallback");
+    }
+}
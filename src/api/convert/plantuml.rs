@@ -0,0 +1,235 @@
+use serde_json::Map;
+
+use crate::{
+    error::MyError,
+    model::{new_node_id, MindMapData, NodeObj, NodeStyle},
+};
+
+use super::push_node;
+
+/// icon as PlantUML emoji of its code points, e.g. `<:1f4a1:>`
+fn emoji(icon: &str) -> String {
+    format!("<:{}:>", icon.chars().map(|c| format!("{:x}", c as u32)).collect::<Vec<String>>().join("-"))
+}
+
+/// icon of PlantUML emoji `<:1f4a1:>` or OpenIconic `<&flag>`, emoji names like `<:smile:>` are kept as they are
+fn icon(name: &str) -> String {
+    let chars: Option<String> = name.split('-').map(|c| u32::from_str_radix(c, 16).ok().and_then(char::from_u32)).collect();
+    chars.unwrap_or_else(|| name.to_string())
+}
+
+/// css background as PlantUML color, e.g. `#ffbbcc` or `#orange`, other css colors are dropped
+fn color(background: &str) -> Option<String> {
+    let background = background.trim();
+    if background.starts_with('#') {
+        Some(background.to_string())
+    } else if !background.is_empty() && background.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(format!("#{}", background))
+    } else {
+        None
+    }
+}
+
+/// write node and descendants, one line for each node, `marker` is `*`, `+` for right side or `-` for left side
+/// icons are written before the topic, hyperlink becomes `[[link topic]]`, multi-line topic uses `:topic;`
+fn write_node(node: &NodeObj, marker: char, depth: usize, sides: &[u8], out: &mut String) {
+    out.push_str(&marker.to_string().repeat(depth));
+    if let Some(c) = node.style.as_ref().and_then(|s| s.background.as_deref()).and_then(color) {
+        out.push_str(&format!("[{}]", c));
+    }
+    let mut text: Vec<String> = node.icons.iter().map(|i| emoji(i)).collect();
+    match &node.hyper_link {
+        Some(link) if node.topic.is_empty() => text.push(format!("[[{}]]", link)),
+        Some(link) => text.push(format!("[[{} {}]]", link, node.topic)),
+        None => text.push(node.topic.clone()),
+    }
+    let text = text.join(" ");
+    if text.contains('\n') {
+        out.push_str(&format!(":{};\n", text));
+    } else {
+        out.push_str(&format!(" {}\n", text));
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        let marker = match sides.get(i) {
+            Some(0) => '-',
+            Some(_) => '+',
+            None => marker,
+        };
+        write_node(child, marker, depth + 1, &[], out);
+    }
+}
+
+/// convert mindmap to PlantUML `@startmindmap`
+/// all nodes use `*` if every main node is on the right, otherwise `+` and `-` give the side of each main node
+/// tags, notes, arrows and summaries have no PlantUML equivalent and are dropped
+pub fn to_plantuml(data: &MindMapData) -> String {
+    let sides = data.main_sides();
+    let mut out = String::from("@startmindmap\n");
+    if sides.contains(&0) {
+        write_node(&data.node_data, '+', 1, &sides, &mut out);
+    } else {
+        write_node(&data.node_data, '*', 1, &[], &mut out);
+    }
+    out.push_str("@endmindmap\n");
+    out
+}
+
+/// parse text written by `write_node`, return (topic, icons, hyperlink)
+/// leading `<:emoji:>` and `<&icon>` are icons, `[[link topic]]` is a hyperlink
+fn parse_text(text: &str) -> (String, Vec<String>, Option<String>) {
+    let mut rest = text.trim();
+    let mut icons = Vec::new();
+    loop {
+        if let Some((name, r)) = rest.strip_prefix("<:").and_then(|r| r.split_once(":>")) {
+            icons.push(icon(name));
+            rest = r.trim_start();
+        } else if let Some((name, r)) = rest.strip_prefix("<&").and_then(|r| r.split_once('>')) {
+            icons.push(name.to_string());
+            rest = r.trim_start();
+        } else {
+            break
+        }
+    }
+    match rest.strip_prefix("[[").and_then(|r| r.strip_suffix("]]")) {
+        Some(link) => {
+            let (url, topic) = link.split_once(' ').unwrap_or((link, ""));
+            let url = url.split('{').next().unwrap_or(url); // `[[url{tooltip} topic]]`
+            (topic.trim().to_string(), icons, Some(url.to_string()))
+        },
+        None => (rest.to_string(), icons, None),
+    }
+}
+
+/// PlantUML color as css color, `#FFBBCC` stays, `#Orange` becomes `orange`
+fn css_color(color: &str) -> String {
+    let name = color.trim_start_matches('#');
+    if matches!(name.len(), 3 | 6 | 8) && name.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("#{}", name)
+    } else {
+        name.to_lowercase()
+    }
+}
+
+/// parse PlantUML mindmap, the part between `@startmindmap` and `@endmindmap` if present, every node gets a new id
+/// `*` nodes are on the right or after `left side` on the left, `+` nodes are on the right and `-` nodes on the left
+/// a single top-level node becomes the root, otherwise a root is added with topic `root_topic`
+pub fn from_plantuml(text: &str, root_topic: &str) -> Result<MindMapData, MyError> {
+    let mut forest: Vec<NodeObj> = Vec::new();
+    let mut stack: Vec<(usize, Vec<usize>)> = Vec::new(); // (depth, path in forest)
+    let mut started = !text.contains("@startmindmap");
+    let mut left = false; // after `left side`
+    let mut block_end: Option<&str> = None; // skip `<style>` and `legend` blocks
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if !started {
+            started = trimmed.starts_with("@startmindmap");
+            continue
+        }
+        if trimmed.starts_with("@endmindmap") {
+            break
+        }
+        if let Some(end) = block_end {
+            if trimmed.to_lowercase().replace(' ', "").starts_with(end) {
+                block_end = None;
+            }
+            continue
+        }
+        match trimmed {
+            "left side" => left = true,
+            "right side" => left = false,
+            t if t.starts_with("<style>") && !t.contains("</style>") => block_end = Some("</style>"),
+            t if t.starts_with("legend") => block_end = Some("endlegend"),
+            _ => (),
+        }
+        let marker = match trimmed.chars().next() {
+            Some(c) if matches!(c, '*' | '+' | '-') => c,
+            _ => continue, // title, skinparam, comments and other commands
+        };
+        let depth = trimmed.chars().take_while(|c| *c == marker).count();
+        let mut rest = &trimmed[depth..];
+        let mut background = None;
+        if let Some((c, r)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            background = Some(css_color(c));
+            rest = r;
+        }
+        rest = rest.strip_prefix('_').unwrap_or(rest); // boxless node
+        let content = match rest.strip_prefix(':') {
+            Some(r) => {
+                let mut content = r.to_string();
+                while !content.trim_end().ends_with(';') {
+                    match lines.next() {
+                        Some(l) => {
+                            content.push('\n');
+                            content.push_str(l.trim());
+                        },
+                        None => break,
+                    }
+                }
+                let content = content.trim_end();
+                content.strip_suffix(';').unwrap_or(content).to_string()
+            },
+            None => rest.to_string(),
+        };
+        let (topic, icons, hyper_link) = parse_text(&content);
+        let direction = match marker {
+            '+' => Some(1),
+            '-' => Some(0),
+            _ => left.then_some(0),
+        };
+        let node = NodeObj{
+            id: new_node_id(),
+            topic,
+            icons,
+            hyper_link,
+            style: background.map(|b| NodeStyle{background: Some(b), ..Default::default()}),
+            direction: if depth == 2 { direction } else { None },
+            ..Default::default()
+        };
+        push_node(&mut forest, &mut stack, depth, node);
+    }
+    let mut root = match forest.len() {
+        0 => return Err(MyError::ValidationError{reason: "no node found in plantuml".to_string()}),
+        1 => forest.pop().unwrap(),
+        _ => NodeObj{id: new_node_id(), topic: root_topic.to_string(), children: forest, ..Default::default()},
+    };
+    // side mode only if some main node is on the left
+    let sides = root.children.iter().any(|c| c.direction == Some(0));
+    for child in &mut root.children {
+        child.direction = if sides { Some(child.direction.unwrap_or(1)) } else { None };
+    }
+    let data = MindMapData{node_data: root, arrows: Vec::new(), summaries: Vec::new(), direction: sides.then_some(2), theme: None, extra: Map::new()};
+    data.validate()?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// node data without ids, plantuml import gives every node a new id
+    fn without_ids(node: &NodeObj) -> serde_json::Value {
+        let mut value = serde_json::to_value(node).unwrap();
+        value.as_object_mut().unwrap().remove("id");
+        if let Some(children) = value.get_mut("children") {
+            *children = node.children.iter().map(without_ids).collect();
+        }
+        value
+    }
+
+    #[test]
+    fn round_trip_keeps_sides_icons_links_and_colors() {
+        let data = MindMapData::parse(r##"{"nodeData":{"id":"root","topic":"root","children":[
+            {"id":"a","topic":"left","direction":0,"icons":["💡"],"style":{"background":"#ffbbcc"},"children":[{"id":"a1","topic":"two\nlines"}]},
+            {"id":"b","topic":"right","direction":1,"hyperLink":"https://example.com/?a=1","children":[{"id":"b1","topic":"deep","style":{"background":"orange"}}]}
+        ]},"direction":2}"##).unwrap();
+        let puml = to_plantuml(&data);
+        assert!(puml.contains("\n--[#ffbbcc] <:1f4a1:> left\n") && puml.contains("\n++ [[https://example.com/?a=1 right]]\n"));
+        let back = from_plantuml(&puml, "fallback").unwrap();
+        assert_eq!(without_ids(&back.node_data), without_ids(&data.node_data));
+        assert_eq!(back.main_sides(), [0, 1]);
+        let right = from_plantuml(&to_plantuml(&MindMapData{direction: None, ..data}), "fallback").unwrap();
+        assert_eq!(right.direction, None);
+        assert!(right.node_data.children.iter().all(|c| c.direction.is_none()));
+    }
+}
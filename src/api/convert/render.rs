@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+
+use once_cell::sync::Lazy;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, TextStr};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb::Database, Group, ImageHrefResolver, Node, Options, PostProcessingSteps, Tree, TreeParsing, TreePostProc},
+};
+use tracing::{event, Level};
+
+use crate::{
+    error::MyError,
+    model::MindMapData,
+    parse_paras::PARAS,
+};
+
+use super::svg::to_svg;
+
+/// family of the embedded Noto Sans, `sans-serif` of the svg if there is no `--font`
+const EMBEDDED_FAMILY: &str = "Noto Sans";
+
+/// Noto Sans SC, it also has the kana and kanji of Japanese, only embedded with `--features cjk-font`
+#[cfg(feature = "cjk-font")]
+const CJK_FONT: &[u8] = include_bytes!("../../../assets/fonts/NotoSansSC-Regular.otf");
+
+/// png is rendered at this scale for high dpi screens, smaller if the image would have more than `MAX_PIXELS` pixels
+const PNG_SCALE: f32 = 2.0;
+const MAX_PIXELS: f32 = 32_000_000.0;
+
+/// pdf page is A4 landscape in points, the map is drawn inside the margin
+const PAGE_WIDTH: f32 = 842.0;
+const PAGE_HEIGHT: f32 = 595.0;
+const PAGE_MARGIN: f32 = 28.0;
+
+/// map is shrunk to one page if it is readable at this scale, larger map is split into as many pages as needed at this scale
+const MIN_SCALE: f32 = 0.4;
+
+/// fonts of png and pdf, text becomes paths so nothing depends on fonts of the viewer
+/// embedded Noto Sans makes latin text work without any font installed, CJK text needs the `cjk-font` feature, a system font or `--font`
+/// system fonts and `--font` are only used for characters the embedded fonts do not have, or if `--font` is given for `sans-serif`
+/// text that no font has is an error instead of boxes, see `check_glyphs`
+static FONTS: Lazy<Database> = Lazy::new(|| {
+    let mut fonts = Database::new();
+    fonts.load_font_data(notosans::REGULAR_TTF.to_vec());
+    fonts.load_font_data(notosans::BOLD_TTF.to_vec());
+    #[cfg(feature = "cjk-font")]
+    fonts.load_font_data(CJK_FONT.to_vec());
+    fonts.load_system_fonts();
+    fonts.set_sans_serif_family(EMBEDDED_FAMILY);
+    if let Some(path) = &PARAS.font {
+        let before = fonts.len();
+        if path.is_dir() {
+            fonts.load_fonts_dir(path);
+        } else if let Err(e) = fonts.load_font_file(path) {
+            event!(Level::WARN, "load font {}: {}", path.display(), e);
+        }
+        // `sans-serif` is the last of the svg font families, so the given font is used unless a listed CJK font is installed
+        let family = fonts.faces().nth(before).and_then(|f| f.families.first()).map(|(family, _)| family.clone());
+        if let Some(family) = family {
+            fonts.set_sans_serif_family(family);
+        }
+    }
+    event!(Level::INFO, "{} font faces loaded for png and pdf", fonts.len());
+    fonts
+});
+
+/// characters of all text in `group`
+fn collect_chars(group: &Group, chars: &mut BTreeSet<char>) {
+    for node in &group.children {
+        match node {
+            Node::Group(g) => collect_chars(g, chars),
+            Node::Text(t) => chars.extend(t.chunks.iter().flat_map(|c| c.text.chars())),
+            _ => {},
+        }
+    }
+}
+
+/// arrows, symbols and emoji, e.g. icons and the link mark, drawn as boxes they lose no text
+fn is_pictograph(c: char) -> bool {
+    matches!(c, '\u{2190}'..='\u{2BFF}' | '\u{1F000}'..='\u{1FAFF}')
+}
+
+/// check every character of the svg text is in one of the fonts, missing glyphs would be drawn as boxes
+/// missing text is an error, missing pictographs are only logged
+fn check_glyphs(tree: &Tree) -> Result<(), MyError> {
+    let mut chars = BTreeSet::new();
+    collect_chars(&tree.root, &mut chars);
+    // joiners and variation selectors of emoji sequences have no glyph
+    chars.retain(|c| !(c.is_whitespace() || c.is_control() || matches!(c, '\u{200B}'..='\u{200F}' | '\u{FE00}'..='\u{FE0F}' | '\u{E0000}'..='\u{E007F}')));
+    for face in FONTS.faces() {
+        if chars.is_empty() {
+            break
+        }
+        FONTS.with_face_data(face.id, |data, index| {
+            if let Ok(f) = ttf_parser::Face::parse(data, index) {
+                chars.retain(|c| f.glyph_index(*c).is_none());
+            }
+        });
+    }
+    let (pictographs, text): (String, String) = chars.into_iter().partition(|c| is_pictograph(*c));
+    if !pictographs.is_empty() {
+        event!(Level::WARN, "no font has {}, they are drawn as boxes", pictographs);
+    }
+    if text.is_empty() {
+        Ok(())
+    } else {
+        Err(MyError::RenderError{reason: format!("no font has {}, install a font for them, e.g. Noto Sans CJK, give one with --font, or build with --features cjk-font", text)})
+    }
+}
+
+/// parse the svg of mindmap and convert its text to paths
+/// only images embedded as data url are drawn, image urls are neither fetched nor read from local files
+fn tree(data: &MindMapData) -> Result<Tree, MyError> {
+    let options = Options{
+        image_href_resolver: ImageHrefResolver{
+            resolve_data: ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..Options::default()
+    };
+    let mut tree = match Tree::from_str(&to_svg(data), &options) {
+        Ok(t) => t,
+        Err(e) => return Err(MyError::RenderError{reason: e.to_string()}),
+    };
+    check_glyphs(&tree)?;
+    tree.postprocess(PostProcessingSteps::default(), &FONTS);
+    Ok(tree)
+}
+
+/// render mindmap as png
+pub fn to_png(data: &MindMapData) -> Result<Vec<u8>, MyError> {
+    let tree = tree(data)?;
+    let (width, height) = (tree.size.width(), tree.size.height());
+    let scale = PNG_SCALE.min((MAX_PIXELS / (width * height)).sqrt());
+    let (pixel_width, pixel_height) = ((width * scale).ceil() as u32, (height * scale).ceil() as u32);
+    let mut pixmap = match Pixmap::new(pixel_width, pixel_height) {
+        Some(p) => p,
+        None => return Err(MyError::RenderError{reason: format!("can not create {}x{} image", pixel_width, pixel_height)}),
+    };
+    resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    match pixmap.encode_png() {
+        Ok(png) => Ok(png),
+        Err(e) => Err(MyError::RenderError{reason: e.to_string()}),
+    }
+}
+
+/// render mindmap as pdf of A4 landscape pages, `title` is the document title
+/// the map is one vector image, each page shows a part of it from left to right, then top to bottom
+pub fn to_pdf(data: &MindMapData, title: &str) -> Result<Vec<u8>, MyError> {
+    let tree = tree(data)?;
+    let (width, height) = (tree.size.width(), tree.size.height());
+    let (area_width, area_height) = (PAGE_WIDTH - 2.0 * PAGE_MARGIN, PAGE_HEIGHT - 2.0 * PAGE_MARGIN);
+    let (columns, rows) = if (area_width / width).min(area_height / height) >= MIN_SCALE {
+        (1, 1)
+    } else {
+        ((width * MIN_SCALE / area_width).ceil() as usize, (height * MIN_SCALE / area_height).ceil() as usize)
+    };
+    // fill the pages as much as possible, the map is centered in all pages
+    let scale = (columns as f32 * area_width / width).min(rows as f32 * area_height / height).min(1.0);
+    let left = PAGE_MARGIN + (columns as f32 * area_width - width * scale) / 2.0;
+    let top = PAGE_HEIGHT - PAGE_MARGIN - (rows as f32 * area_height - height * scale) / 2.0;
+    let mut pdf = Pdf::new();
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let info_id = Ref::new(3);
+    let map_id = Ref::new(4);
+    let map_name = Name(b"Map");
+    let mut next_id = svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut pdf, map_id);
+    let mut page_ids = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            // bottom left corner of the map on this page
+            let (x, y) = (left - column as f32 * area_width, top + row as f32 * area_height - height * scale);
+            let mut content = Content::new();
+            content.save_state();
+            content.rect(PAGE_MARGIN, PAGE_MARGIN, area_width, area_height);
+            content.clip_nonzero();
+            content.end_path();
+            content.transform([width * scale, 0.0, 0.0, height * scale, x, y]);
+            content.x_object(map_name);
+            content.restore_state();
+            let page_id = next_id.bump();
+            let content_id = next_id.bump();
+            pdf.stream(content_id, &content.finish());
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            page.resources().x_objects().pair(map_name, map_id);
+            page.finish();
+            page_ids.push(page_id);
+        }
+    }
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).count(page_ids.len() as i32).kids(page_ids);
+    pdf.document_info(info_id).title(TextStr(title));
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_glyph_is_error() {
+        // U+0378 is unassigned, no font has it
+        let data = MindMapData::parse(r#"{"nodeData":{"id":"root","topic":"root ͸"}}"#).unwrap();
+        match to_png(&data) {
+            Err(MyError::RenderError{reason}) => assert!(reason.contains('\u{378}'), "{}", reason),
+            _ => panic!("missing glyph rendered"),
+        }
+        let data = MindMapData::parse(r#"{"nodeData":{"id":"root","topic":"root","children":[{"id":"a","topic":"a"}]}}"#).unwrap();
+        assert!(to_png(&data).unwrap().starts_with(b"\x89PNG"));
+        assert!(to_pdf(&data, "root").unwrap().starts_with(b"%PDF"));
+    }
+
+    #[cfg(feature = "cjk-font")]
+    #[test]
+    fn cjk_text_uses_embedded_font() {
+        let data = MindMapData::parse(r#"{"nodeData":{"id":"root","topic":"思维导图","children":[{"id":"a","topic":"日本語のテキスト"}]}}"#).unwrap();
+        assert!(to_png(&data).unwrap().starts_with(b"\x89PNG"));
+        assert!(to_pdf(&data, "思维导图").unwrap().starts_with(b"%PDF"));
+    }
+}
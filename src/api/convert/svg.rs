@@ -0,0 +1,325 @@
+use serde_json::{Map, Value};
+
+use crate::{
+    escape::escape_xml,
+    model::{Delta, MindMapData, Tag},
+};
+
+use super::layout::{layout, tag_height, text_width, Layout, Placed, Rect, Side, FONT_FAMILY, LABEL_FONT_SIZE, TAG_FONT_SIZE};
+
+/// default arrow color and label color of mind-elixir
+const ARROW_COLOR: &str = "rgb(227, 125, 116)";
+const ARROW_LABEL_COLOR: &str = "rgb(235, 95, 82)";
+
+/// number for svg attribute, at most 2 decimals
+fn num(n: f64) -> String {
+    let s = format!("{:.2}", n);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+/// string value of a style map, e.g. arrow `style.stroke`
+fn style_str<'a>(style: Option<&'a Map<String, Value>>, key: &str) -> Option<&'a str> {
+    style.and_then(|s| s.get(key)).and_then(|v| v.as_str())
+}
+
+/// curve from the root to a main node, same as mind-elixir `generateMainBranch`
+fn main_branch(root: &Rect, child: &Placed, height: f64) -> String {
+    let (c, u) = (root.x + root.w / 2.0, root.y + root.h / 2.0);
+    let v = child.tpc.y + child.tpc.h / 2.0;
+    let offset = (1.0 - (v - u).abs() / height) * 0.25 * (root.w / 2.0);
+    let (h, g) = match child.side {
+        Side::Left => (c - root.w / 10.0 - offset, child.tpc.right()),
+        Side::Right => (c + root.w / 10.0 + offset, child.tpc.x),
+    };
+    format!("M {} {} Q {} {} {} {}", num(h), num(u), num(h), num(v), num(g), num(v))
+}
+
+/// line from a node to its child, under the child topic, same as mind-elixir `generateSubBranch`
+/// lines from a main node start at its middle, other lines start at the bottom of the parent
+fn sub_branch(parent: &Placed, child: &Placed, gap: f64) -> String {
+    let u = if parent.depth == 1 { parent.parent.y + parent.parent.h / 2.0 } else { parent.parent.bottom() };
+    let v = child.parent.bottom();
+    let f = (u - v).abs() / 300.0 * gap;
+    let (d, m, p, g, c) = match child.side {
+        Side::Left => (parent.parent.x, parent.parent.x + gap, parent.parent.x - gap, child.parent.x + gap, parent.parent.x + f),
+        Side::Right => (parent.parent.right(), parent.parent.right() - gap, parent.parent.right() + gap, child.parent.right() - gap, parent.parent.right() - f),
+    };
+    format!("M {} {} C {} {} {} {} {} {} H {}", num(m), num(u), num(d), num(u), num(c), num(v), num(p), num(v), num(g))
+}
+
+/// path element
+fn path(d: &str, stroke: &str, width: &str, extra: &str) -> String {
+    format!("<path d=\"{}\" stroke=\"{}\" fill=\"none\" stroke-width=\"{}\"{}/>\n", d, escape_xml(stroke), width, extra)
+}
+
+/// text element centered on `y`, `anchor` is start, middle or end
+fn text(x: f64, y: f64, size: f64, color: &str, anchor: &str, bold: bool, content: &str) -> String {
+    format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\" dominant-baseline=\"central\"{} xml:space=\"preserve\">{}</text>\n",
+        num(x), num(y), num(size), escape_xml(color), anchor, if bold { " font-weight=\"bold\"" } else { "" }, escape_xml(content),
+    )
+}
+
+/// lines from node to its children and their descendants
+fn write_sub_lines(node: &Placed, gap: f64, out: &mut String) {
+    for child in &node.children {
+        out.push_str(&path(&sub_branch(node, child, gap), &child.branch, "2", ""));
+        write_sub_lines(child, gap, out);
+    }
+}
+
+/// point where the line from the center of `tpc` to its control point leaves the box, same as mind-elixir
+fn edge_point(tpc: &Rect, ctrl: (f64, f64)) -> (f64, f64) {
+    let (cx, cy) = (tpc.x + tpc.w / 2.0, tpc.y + tpc.h / 2.0);
+    let o = (cy - ctrl.1) / (ctrl.0 - cx);
+    if o > tpc.h / tpc.w || o < -tpc.h / tpc.w {
+        if cy - ctrl.1 < 0.0 { (cx - tpc.h / 2.0 / o, cy + tpc.h / 2.0) } else { (cx + tpc.h / 2.0 / o, cy - tpc.h / 2.0) }
+    } else if cx - ctrl.0 < 0.0 {
+        (cx + tpc.w / 2.0, cy - tpc.w * o / 2.0)
+    } else {
+        (cx - tpc.w / 2.0, cy + tpc.w * o / 2.0)
+    }
+}
+
+/// control point of the arrow end at `tpc`, `delta` is the offset from its center
+/// zero offset, e.g. arrows imported from other formats, has no direction, so the offset of a new arrow in mind-elixir is used
+fn control_point(tpc: &Rect, delta: &Delta) -> (f64, f64) {
+    let (x, y) = if delta.x == 0.0 && delta.y == 0.0 { (tpc.w / 2.0 + 100.0, 0.0) } else { (delta.x, delta.y) };
+    (tpc.x + tpc.w / 2.0 + x, tpc.y + tpc.h / 2.0 + y)
+}
+
+/// arrow head at (`x`, `y`) pointing away from the control point, None if they are the same point
+fn arrow_head(ctrl: (f64, f64), x: f64, y: f64) -> Option<String> {
+    let (s, i) = (y - ctrl.1, ctrl.0 - x);
+    let mut angle = (s.abs() / i.abs()).atan().to_degrees();
+    if angle.is_nan() {
+        return None
+    }
+    if i < 0.0 && s > 0.0 {
+        angle = 180.0 - angle;
+    }
+    if i < 0.0 && s < 0.0 {
+        angle += 180.0;
+    }
+    if i > 0.0 && s < 0.0 {
+        angle = 360.0 - angle;
+    }
+    let point = |a: f64| (x + a.to_radians().cos() * 12.0, y - a.to_radians().sin() * 12.0);
+    let (x1, y1) = point(angle + 30.0);
+    let (x2, y2) = point(angle - 30.0);
+    Some(format!("M {} {} L {} {} L {} {}", num(x1), num(y1), num(x), num(y), num(x2), num(y2)))
+}
+
+/// arrows between nodes, dashed curves with their labels in the middle, arrows to hidden nodes are skipped
+fn write_arrows(data: &MindMapData, layout: &Layout, out: &mut String) {
+    for arrow in &data.arrows {
+        let (from, to) = match (layout.root.find(&arrow.from), layout.root.find(&arrow.to)) {
+            (Some(f), Some(t)) => (f.tpc, t.tpc),
+            _ => continue,
+        };
+        let ctrl1 = control_point(&from, &arrow.delta1);
+        let ctrl2 = control_point(&to, &arrow.delta2);
+        let (x1, y1) = edge_point(&from, ctrl1);
+        let (x2, y2) = edge_point(&to, ctrl2);
+        let style = arrow.style.as_ref();
+        let stroke = style_str(style, "stroke").unwrap_or(ARROW_COLOR);
+        let width = match style.and_then(|s| s.get("strokeWidth")) {
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::String(s)) => s.clone(),
+            _ => "2".to_string(),
+        };
+        let dash = style_str(style, "strokeDasharray").unwrap_or("8,2");
+        let mut heads = vec![arrow_head(ctrl2, x2, y2)];
+        if arrow.bidirectional == Some(true) {
+            heads.push(arrow_head(ctrl1, x1, y1));
+        }
+        let d = format!("M {} {} C {} {} {} {} {} {}", num(x1), num(y1), num(ctrl1.0), num(ctrl1.1), num(ctrl2.0), num(ctrl2.1), num(x2), num(y2));
+        out.push_str(&path(&d, stroke, &escape_xml(&width), &format!(" stroke-dasharray=\"{}\"", escape_xml(dash))));
+        for head in heads.into_iter().flatten() {
+            out.push_str(&path(&head, stroke, &escape_xml(&width), ""));
+        }
+        if !arrow.label.is_empty() {
+            // middle of the bezier curve
+            let x = x1 / 8.0 + ctrl1.0 * 3.0 / 8.0 + ctrl2.0 * 3.0 / 8.0 + x2 / 8.0;
+            let y = y1 / 8.0 + ctrl1.1 * 3.0 / 8.0 + ctrl2.1 * 3.0 / 8.0 + y2 / 8.0;
+            let color = style_str(style, "labelColor").unwrap_or(ARROW_LABEL_COLOR);
+            out.push_str(&text(x, y, LABEL_FONT_SIZE, color, "middle", false, &arrow.label));
+        }
+    }
+}
+
+/// summary brackets beside children `start..=end` with their labels, same as mind-elixir
+fn write_summaries(data: &MindMapData, layout: &Layout, out: &mut String) {
+    let color = layout.theme.var("--color");
+    for summary in &data.summaries {
+        let parent = match layout.root.find(&summary.parent) {
+            Some(p) if summary.start <= summary.end && summary.end < p.children.len() => p,
+            _ => continue,
+        };
+        let children = &parent.children[summary.start..=summary.end];
+        let side = if parent.depth == 0 { children[0].side } else { parent.side };
+        let margin = if summary.start == summary.end { 10.0 } else { 20.0 };
+        let offset = if parent.depth == 0 { 0.0 } else { 10.0 };
+        let top = children[0].wrapper.y + margin + offset;
+        let bottom = children[children.len() - 1].wrapper.bottom() - margin + offset;
+        let middle = (top + bottom) / 2.0;
+        let left = children.iter().map(|c| c.wrapper.x).fold(f64::INFINITY, f64::min);
+        let right = children.iter().map(|c| c.wrapper.right()).fold(0.0, f64::max);
+        let style = summary.style.as_ref();
+        let stroke = style_str(style, "stroke").unwrap_or(color);
+        let label_color = style_str(style, "labelColor").unwrap_or(color);
+        let (d, x, anchor) = match side {
+            Side::Left => (
+                format!("M {} {} c -5 0 -10 5 -10 10 L {} {} c 0 5 5 10 10 10 M {} {} h -10", num(left + 10.0), num(top), num(left), num(bottom - 10.0), num(left), num(middle)),
+                left - 20.0,
+                "end",
+            ),
+            Side::Right => (
+                format!("M {} {} c 5 0 10 5 10 10 L {} {} c 0 5 -5 10 -10 10 M {} {} h 10", num(right - 10.0), num(top), num(right), num(bottom - 10.0), num(right), num(middle)),
+                right + 20.0,
+                "start",
+            ),
+        };
+        out.push_str(&path(&d, stroke, "2", " stroke-linecap=\"round\""));
+        out.push_str(&text(x, middle, LABEL_FONT_SIZE, label_color, anchor, false, &summary.label));
+    }
+}
+
+/// background and text color of a tag, tag style may set them
+fn tag_colors(tag: &Tag) -> (&str, &str) {
+    let style = match tag {
+        Tag::Object{style, ..} => style.as_ref(),
+        Tag::Text(_) => None,
+    };
+    (
+        style_str(style, "background").or_else(|| style_str(style, "backgroundColor")).unwrap_or("#d6f0f8"),
+        style_str(style, "color").unwrap_or("#276f86"),
+    )
+}
+
+/// topic box of node with image, text, link, icons and tags, collapsed node has a `+` beside it
+fn write_node(node: &Placed, layout: &Layout, out: &mut String) {
+    let theme = &layout.theme;
+    let (tpc, topic) = (&node.tpc, &node.topic);
+    let style = node.node.style.as_ref();
+    let background = style.and_then(|s| s.background.as_deref());
+    let color = style.and_then(|s| s.color.as_deref());
+    let (fill, stroke, color, radius) = match node.depth {
+        0 => (background.or(Some(theme.var("--root-bgcolor"))), Some(theme.var("--root-border-color")), color.unwrap_or(theme.var("--root-color")), theme.px("--root-radius")),
+        1 => (background.or(Some(theme.var("--main-bgcolor"))), Some(node.branch.as_str()), color.unwrap_or(theme.var("--main-color")), theme.px("--main-radius")),
+        _ => (background, None, color.unwrap_or(theme.var("--color")), 3.0),
+    };
+    out.push_str("<g>\n");
+    if let Some(note) = &node.node.note {
+        out.push_str(&format!("<title>{}</title>\n", escape_xml(note)));
+    }
+    if fill.is_some() || stroke.is_some() {
+        // stroke is drawn inside the box like a css border
+        let b = topic.border;
+        out.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" fill=\"{}\"",
+            num(tpc.x + b / 2.0), num(tpc.y + b / 2.0), num(tpc.w - b), num(tpc.h - b), num(radius.min(tpc.h / 2.0)), escape_xml(fill.unwrap_or("none")),
+        ));
+        match stroke {
+            Some(s) if b > 0.0 => out.push_str(&format!(" stroke=\"{}\" stroke-width=\"{}\"/>\n", escape_xml(s), num(b))),
+            _ => out.push_str("/>\n"),
+        }
+    }
+    let x = tpc.x + topic.border + topic.padding.1;
+    let mut y = tpc.y + topic.border + topic.padding.0;
+    if let Some(image) = &node.node.image {
+        out.push_str(&format!(
+            "<image href=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"xMidYMid slice\"/>\n",
+            escape_xml(&image.url), num(x), num(y), num(image.width), num(image.height),
+        ));
+        y += image.height + 8.0;
+    }
+    for line in &topic.lines {
+        out.push_str(&text(x, y + topic.line_height / 2.0, topic.font_size, color, "start", topic.bold, line));
+        y += topic.line_height;
+    }
+    // link and icons follow the last line
+    let line_y = y - topic.line_height / 2.0;
+    let mut line_x = x + topic.line_widths.last().copied().unwrap_or(0.0);
+    if let Some(link) = &node.node.hyper_link {
+        line_x += 0.3 * topic.font_size;
+        out.push_str(&format!("<a href=\"{}\">\n", escape_xml(link)));
+        out.push_str(&text(line_x, line_y, topic.font_size, theme.var("--selected"), "start", false, "🔗"));
+        out.push_str("</a>\n");
+        line_x += text_width("🔗", topic.font_size, false);
+    }
+    if !node.node.icons.is_empty() {
+        out.push_str(&text(line_x + 5.0, line_y, topic.font_size, color, "start", false, &node.node.icons.concat()));
+    }
+    let mut tag_x = x;
+    for (tag, width) in node.node.tags.iter().zip(&topic.tag_widths) {
+        let (background, color) = tag_colors(tag);
+        out.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"3\" fill=\"{}\"/>\n",
+            num(tag_x), num(y + 2.0), num(*width), num(tag_height()), escape_xml(background),
+        ));
+        out.push_str(&text(tag_x + 4.0, y + 2.0 + tag_height() / 2.0, TAG_FONT_SIZE, color, "start", false, tag.text()));
+        tag_x += width + 4.0;
+    }
+    if node.collapsed() {
+        let p = &node.parent;
+        let (cx, cy) = match (node.depth, node.side) {
+            (1, Side::Right) => (p.right() + 1.0, p.y + p.h / 2.0),
+            (1, Side::Left) => (p.x - 1.0, p.y + p.h / 2.0),
+            (_, Side::Right) => (p.right() - 14.0, p.bottom()),
+            (_, Side::Left) => (p.x + 14.0, p.bottom()),
+        };
+        out.push_str(&format!(
+            "<g opacity=\"0.8\"><circle cx=\"{}\" cy=\"{}\" r=\"7.5\" fill=\"white\" stroke=\"black\"/><path d=\"M {} {} h 7 M {} {} v 7\" stroke=\"black\" stroke-width=\"1.2\"/></g>\n",
+            num(cx), num(cy), num(cx - 3.5), num(cy), num(cx), num(cy - 3.5),
+        ));
+    }
+    out.push_str("</g>\n");
+}
+
+/// render mindmap as svg, laid out like mind-elixir with the theme colors, tags, icons, arrows and summaries
+/// text sizes are estimated, so long topics may be a little wider or narrower than in the browser
+pub fn to_svg(data: &MindMapData) -> String {
+    let layout = layout(data);
+    let (width, height) = (num(layout.width.ceil()), num(layout.height.ceil()));
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"{}\">\n",
+        width, height, width, height, escape_xml(FONT_FAMILY),
+    );
+    out.push_str(&format!("<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n", escape_xml(layout.theme.var("--bgcolor"))));
+    let gap = layout.theme.px("--node-gap-x");
+    for main in &layout.root.children {
+        write_sub_lines(main, gap, &mut out);
+    }
+    for main in &layout.root.children {
+        out.push_str(&path(&main_branch(&layout.root.tpc, main, layout.height), &main.branch, "3", ""));
+    }
+    write_arrows(data, &layout, &mut out);
+    write_summaries(data, &layout, &mut out);
+    for node in layout.root.descendants() {
+        write_node(node, &layout, &mut out);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_without_delta_have_no_nan() {
+        // delta1 and delta2 default to {0, 0}, same as arrows imported from XMind or FreeMind
+        let data = MindMapData::parse(r#"{"nodeData":{"id":"root","topic":"root","children":[
+            {"id":"a","topic":"a","children":[{"id":"a1","topic":"a1"}]},{"id":"b","topic":"b"}
+        ]},"arrows":[
+            {"id":"x","label":"a to b","from":"a","to":"b","bidirectional":true},
+            {"id":"y","label":"","from":"a1","to":"root","delta1":{"x":0,"y":0},"delta2":{"x":0,"y":0}},
+            {"id":"z","label":"self","from":"b","to":"b"}
+        ]}"#).unwrap();
+        let svg = to_svg(&data);
+        assert!(!svg.contains("NaN"), "{}", svg);
+        assert_eq!(svg.matches("stroke-dasharray").count(), 3);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Write};
+
+use serde_json::{json, Map, Value};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    error::MyError,
+    model::{new_node_id, Arrow, Delta, MindMapData, NodeObj, NodeStyle, Summary, Tag},
+};
+
+use super::font_size;
+
+/// XMind markers and the emoji shown by mind-elixir
+const MARKERS: [(&str, &str); 24] = [
+    ("priority-1", "1️⃣"),
+    ("priority-2", "2️⃣"),
+    ("priority-3", "3️⃣"),
+    ("priority-4", "4️⃣"),
+    ("priority-5", "5️⃣"),
+    ("priority-6", "6️⃣"),
+    ("priority-7", "7️⃣"),
+    ("priority-8", "8️⃣"),
+    ("priority-9", "9️⃣"),
+    ("task-done", "✅"),
+    ("flag-red", "🚩"),
+    ("star-yellow", "⭐"),
+    ("smiley-smile", "😊"),
+    ("smiley-laugh", "😄"),
+    ("smiley-angry", "😠"),
+    ("smiley-cry", "😢"),
+    ("smiley-surprise", "😮"),
+    ("symbol-question", "❓"),
+    ("symbol-exclam", "❗"),
+    ("symbol-info", "ℹ️"),
+    ("symbol-wrong", "❌"),
+    ("symbol-plus", "➕"),
+    ("symbol-minus", "➖"),
+    ("symbol-idea", "💡"),
+];
+
+/// largest `content.json` read from a workbook, a small zip can inflate to a huge file
+const MAX_CONTENT_BYTES: u64 = 32 * 1024 * 1024;
+
+/// string field of a json object
+fn str_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+/// write node and descendants as XMind topic, summaries of its children are written as `summaries` and `children.summary`
+fn write_topic(node: &NodeObj, summaries: &HashMap<&str, Vec<&Summary>>) -> Value {
+    let mut topic = Map::new();
+    topic.insert("id".to_string(), json!(node.id));
+    topic.insert("class".to_string(), json!("topic"));
+    topic.insert("title".to_string(), json!(node.topic));
+    if let Some(link) = &node.hyper_link {
+        topic.insert("href".to_string(), json!(link));
+    }
+    if !node.tags.is_empty() {
+        topic.insert("labels".to_string(), json!(node.tags.iter().map(|t| t.text()).collect::<Vec<&str>>()));
+    }
+    // icons without XMind marker are dropped, unless they are already marker ids
+    let markers: Vec<Value> = node.icons.iter().filter_map(|icon| match MARKERS.iter().find(|(_, e)| e == icon) {
+        Some((id, _)) => Some(json!({"markerId": id})),
+        None => (icon.is_ascii() && icon.contains('-')).then(|| json!({"markerId": icon})),
+    }).collect();
+    if !markers.is_empty() {
+        topic.insert("markers".to_string(), json!(markers));
+    }
+    if let Some(note) = &node.note {
+        topic.insert("notes".to_string(), json!({"plain": {"content": note}}));
+    }
+    if node.expanded == Some(false) && !node.children.is_empty() {
+        topic.insert("branch".to_string(), json!("folded"));
+    }
+    if let Some(style) = &node.style {
+        let mut properties = Map::new();
+        if let Some(color) = &style.color {
+            properties.insert("fo:color".to_string(), json!(color));
+        }
+        if let Some(background) = &style.background {
+            properties.insert("svg:fill".to_string(), json!(background));
+        }
+        if let Some(size) = style.font_size.as_deref().and_then(font_size) {
+            properties.insert("fo:font-size".to_string(), json!(format!("{}pt", size)));
+        }
+        if let Some(weight) = &style.font_weight {
+            properties.insert("fo:font-weight".to_string(), json!(weight));
+        }
+        if !properties.is_empty() {
+            topic.insert("style".to_string(), json!({"id": new_node_id(), "properties": properties}));
+        }
+    }
+    let mut children = Map::new();
+    if !node.children.is_empty() {
+        children.insert("attached".to_string(), Value::Array(node.children.iter().map(|c| write_topic(c, summaries)).collect()));
+    }
+    if let Some(list) = summaries.get(node.id.as_str()) {
+        topic.insert("summaries".to_string(), Value::Array(list.iter().map(|s| json!({
+            "id":      s.id,
+            "range":   format!("({},{})", s.start, s.end),
+            "topicId": format!("{}-topic", s.id),
+        })).collect()));
+        children.insert("summary".to_string(), Value::Array(list.iter().map(|s| json!({
+            "id":    format!("{}-topic", s.id),
+            "class": "topic",
+            "title": s.label,
+        })).collect()));
+    }
+    if !children.is_empty() {
+        topic.insert("children".to_string(), Value::Object(children));
+    }
+    Value::Object(topic)
+}
+
+/// convert mindmap to a one sheet XMind workbook, `title` is the sheet title
+/// arrows are written as relationships, their control points are the arrow deltas
+pub fn to_xmind(data: &MindMapData, title: &str) -> Result<Vec<u8>, MyError> {
+    let mut summaries: HashMap<&str, Vec<&Summary>> = HashMap::new();
+    for summary in &data.summaries {
+        summaries.entry(summary.parent.as_str()).or_default().push(summary);
+    }
+    let mut root = write_topic(&data.node_data, &summaries);
+    let structure = match data.direction {
+        Some(0) => "org.xmind.ui.logic.left",
+        Some(2) => "org.xmind.ui.map.unbalanced",
+        _ => "org.xmind.ui.logic.right",
+    };
+    root["structureClass"] = json!(structure);
+    let mut sheet = json!({
+        "id":        new_node_id(),
+        "class":     "sheet",
+        "title":     title,
+        "rootTopic": root,
+    });
+    if !data.arrows.is_empty() {
+        sheet["relationships"] = Value::Array(data.arrows.iter().map(|a| {
+            let mut relationship = json!({
+                "id":            a.id,
+                "class":         "relationship",
+                "end1Id":        a.from,
+                "end2Id":        a.to,
+                "title":         a.label,
+                "controlPoints": {"0": {"x": a.delta1.x, "y": a.delta1.y}, "1": {"x": a.delta2.x, "y": a.delta2.y}},
+            });
+            if a.bidirectional == Some(true) {
+                relationship["style"] = json!({"id": new_node_id(), "properties": {"arrow-begin-class": "org.xmind.arrowShape.triangle"}});
+            }
+            relationship
+        }).collect());
+    }
+    let content = serde_json::to_string(&json!([sheet])).unwrap();
+    let manifest = json!({"file-entries": {"content.json": {}, "metadata.json": {}}}).to_string();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, text) in [("content.json", content.as_str()), ("metadata.json", "{}"), ("manifest.json", manifest.as_str())] {
+        zip.start_file(name, SimpleFileOptions::default())?;
+        zip.write_all(text.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// control point of a relationship, e.g. `{"x": 10, "y": -20}`
+fn control_point(relationship: &Value, index: &str) -> Delta {
+    match relationship.get("controlPoints").and_then(|c| c.get(index)) {
+        Some(p) => Delta{
+            x: p.get("x").and_then(Value::as_f64).unwrap_or_default(),
+            y: p.get("y").and_then(Value::as_f64).unwrap_or_default(),
+        },
+        None => Delta::default(),
+    }
+}
+
+/// read one XMind topic and its children, detached topics become the last children
+/// summaries of its children are collected into `summaries`, `seen` ids are given new ones before summaries take the id of their parent
+fn read_topic(topic: &Value, seen: &mut HashSet<String>, summaries: &mut Vec<Summary>) -> NodeObj {
+    let id = match str_field(topic, "id") {
+        Some(id) if !id.is_empty() && !seen.contains(id) => id.to_string(),
+        _ => new_node_id(),
+    };
+    seen.insert(id.clone());
+    let mut node = NodeObj{
+        id,
+        topic: str_field(topic, "title").unwrap_or_default().to_string(),
+        ..Default::default()
+    };
+    node.hyper_link = str_field(topic, "href").map(|h| h.to_string());
+    if let Some(labels) = topic.get("labels").and_then(Value::as_array) {
+        node.tags = labels.iter().filter_map(Value::as_str).map(|l| Tag::Text(l.to_string())).collect();
+    }
+    if let Some(markers) = topic.get("markers").and_then(Value::as_array) {
+        node.icons = markers.iter().filter_map(|m| str_field(m, "markerId")).map(|id| match MARKERS.iter().find(|(m, _)| *m == id) {
+            Some((_, emoji)) => emoji.to_string(),
+            None => id.to_string(),
+        }).collect();
+    }
+    node.note = topic.get("notes").and_then(|n| n.get("plain")).and_then(|p| str_field(p, "content")).filter(|n| !n.is_empty()).map(|n| n.to_string());
+    if str_field(topic, "branch") == Some("folded") {
+        node.expanded = Some(false);
+    }
+    if let Some(properties) = topic.get("style").and_then(|s| s.get("properties")) {
+        let style = NodeStyle{
+            color:       str_field(properties, "fo:color").map(|c| c.to_string()),
+            background:  str_field(properties, "svg:fill").map(|c| c.to_string()),
+            font_size:   str_field(properties, "fo:font-size").and_then(font_size).map(|s| format!("{}px", s)),
+            font_weight: str_field(properties, "fo:font-weight").map(|w| w.to_string()),
+            ..Default::default()
+        };
+        if style.color.is_some() || style.background.is_some() || style.font_size.is_some() || style.font_weight.is_some() {
+            node.style = Some(style);
+        }
+    }
+    if let Some(children) = topic.get("children") {
+        for kind in ["attached", "detached"] {
+            if let Some(list) = children.get(kind).and_then(Value::as_array) {
+                node.children.extend(list.iter().map(|c| read_topic(c, seen, summaries)));
+            }
+        }
+        let summary_topics = children.get("summary").and_then(Value::as_array);
+        for summary in topic.get("summaries").and_then(Value::as_array).into_iter().flatten() {
+            let range: Vec<usize> = str_field(summary, "range").unwrap_or_default()
+                .trim_matches(|c| c == '(' || c == ')')
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect();
+            if let [start, end] = range[..] {
+                let topic_id = str_field(summary, "topicId");
+                let label = summary_topics.into_iter().flatten()
+                    .find(|t| topic_id.is_some() && str_field(t, "id") == topic_id)
+                    .and_then(|t| str_field(t, "title"))
+                    .unwrap_or_default();
+                summaries.push(Summary{
+                    id:     str_field(summary, "id").map(|i| i.to_string()).unwrap_or_else(new_node_id),
+                    label:  label.to_string(),
+                    parent: node.id.clone(),
+                    start,
+                    end,
+                    style:  None,
+                    extra:  Map::new(),
+                });
+            }
+        }
+    }
+    node
+}
+
+/// read one sheet, relationships and summaries with missing nodes or out of range are dropped
+/// duplicate topic ids get new ones, relationships to them go to the first topic
+fn read_sheet(sheet: &Value) -> Result<MindMapData, MyError> {
+    let root = match sheet.get("rootTopic") {
+        Some(r) => r,
+        None => return Err(MyError::ValidationError{reason: "invalid xmind: sheet without rootTopic".to_string()}),
+    };
+    let mut summaries = Vec::new();
+    let node_data = read_topic(root, &mut HashSet::new(), &mut summaries);
+    let structure = str_field(root, "structureClass").unwrap_or_default();
+    let direction = if structure.ends_with(".logic.left") {
+        Some(0)
+    } else if structure.ends_with(".logic.right") {
+        Some(1)
+    } else if structure.starts_with("org.xmind.ui.map") {
+        Some(2)
+    } else {
+        None
+    };
+    let arrows = sheet.get("relationships").and_then(Value::as_array).into_iter().flatten().filter_map(|r| Some(Arrow{
+        id:            str_field(r, "id").map(|i| i.to_string()).unwrap_or_else(new_node_id),
+        label:         str_field(r, "title").unwrap_or_default().to_string(),
+        from:          str_field(r, "end1Id")?.to_string(),
+        to:            str_field(r, "end2Id")?.to_string(),
+        delta1:        control_point(r, "0"),
+        delta2:        control_point(r, "1"),
+        bidirectional: r.get("style").and_then(|s| s.get("properties")).and_then(|p| str_field(p, "arrow-begin-class"))
+            .filter(|c| *c != "org.xmind.arrowShape.none")
+            .map(|_| true),
+        style:         None,
+        extra:         Map::new(),
+    })).collect();
+    let mut data = MindMapData{node_data, arrows, summaries, direction, theme: None, extra: Map::new()};
+    let ids: HashSet<String> = data.node_data.descendants().iter().map(|n| n.id.clone()).collect();
+    data.arrows.retain(|a| ids.contains(&a.from) && ids.contains(&a.to));
+    let node_data = &data.node_data;
+    data.summaries.retain(|s| node_data.find(&s.parent).is_some_and(|p| s.start <= s.end && s.end < p.children.len()));
+    data.validate()?;
+    Ok(data)
+}
+
+/// parse XMind workbook, return (sheet title, mindmap) of each sheet
+/// only XMind 2020 and later `content.json` is supported, not XMind 8 `content.xml`
+pub fn from_xmind(content: &[u8]) -> Result<Vec<(String, MindMapData)>, MyError> {
+    let mut archive = match ZipArchive::new(Cursor::new(content)) {
+        Ok(a) => a,
+        Err(e) => return Err(MyError::ValidationError{reason: format!("invalid xmind: {}", e)}),
+    };
+    let mut text = Vec::new();
+    match archive.by_name("content.json") {
+        // the declared size may be wrong, so stop reading just after the limit too
+        Ok(f) => {
+            let too_large = MyError::ValidationError{reason: format!("invalid xmind: content.json is larger than {} MiB", MAX_CONTENT_BYTES / 1024 / 1024)};
+            if f.size() > MAX_CONTENT_BYTES {
+                return Err(too_large)
+            }
+            match f.take(MAX_CONTENT_BYTES + 1).read_to_end(&mut text) {
+                Ok(n) if n as u64 > MAX_CONTENT_BYTES => return Err(too_large),
+                Ok(_) => (),
+                Err(e) => return Err(MyError::ValidationError{reason: format!("invalid xmind content.json: {}", e)}),
+            }
+        },
+        Err(_) => return Err(MyError::ValidationError{reason: "invalid xmind: no content.json, XMind 8 files must be saved by XMind 2020 or later first".to_string()}),
+    };
+    let sheets: Vec<Value> = match serde_json::from_slice(&text) {
+        Ok(s) => s,
+        Err(e) => return Err(MyError::ValidationError{reason: format!("invalid xmind content.json: {}", e)}),
+    };
+    if sheets.is_empty() {
+        return Err(MyError::ValidationError{reason: "no sheet found in xmind".to_string()})
+    }
+    sheets.iter().map(|s| Ok((str_field(s, "title").unwrap_or_default().to_string(), read_sheet(s)?))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// workbook with only `content.json`
+    fn workbook(content: &[u8]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("content.json", SimpleFileOptions::default()).unwrap();
+        zip.write_all(content).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn duplicate_ids_keep_relationship_ends() {
+        let content = r#"[{"title":"sheet","rootTopic":{"id":"root","title":"root","children":{"attached":[
+            {"id":"a","title":"first"},
+            {"id":"a","title":"second","children":{"attached":[{"id":"x","title":"x"},{"id":"y","title":"y"}]},"summaries":[{"id":"s","range":"(0,1)","topicId":"st"}]}
+        ],"summary":[]}},"relationships":[{"id":"r","end1Id":"x","end2Id":"a"}]}]"#;
+        let sheets = from_xmind(&workbook(content.as_bytes())).unwrap();
+        let data = &sheets[0].1;
+        let children = &data.node_data.children;
+        assert_eq!(children[0].id, "a");
+        assert_ne!(children[1].id, "a");
+        assert_eq!((data.arrows[0].from.as_str(), data.arrows[0].to.as_str()), ("x", "a"));
+        assert_eq!(data.summaries[0].parent, children[1].id);
+    }
+
+    #[test]
+    fn large_content_is_rejected() {
+        let mut content = vec![b' '; MAX_CONTENT_BYTES as usize];
+        content.extend_from_slice(b"[]");
+        match from_xmind(&workbook(&content)) {
+            Err(MyError::ValidationError{reason}) => assert!(reason.contains("larger than")),
+            other => panic!("expected validation error, got {:?}", other.map(|s| s.len())),
+        }
+    }
+}
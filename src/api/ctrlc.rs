@@ -1,6 +1,4 @@
-//use std::process::exit;
-
-use crate::DATA;
+use tracing::{event, Level};
 
 /// https://github.com/Finomnis/tokio-graceful-shutdown/blob/main/src/signal_handling.rs
 /// https://stackoverflow.com/questions/73681328/graceful-handling-of-sigterm-ctrl-c-and-shutdown-a-threadpool
@@ -47,10 +45,9 @@ pub async fn wait_for_signal_impl() {
 
 /// Registers signal handlers and waits for a signal that
 /// indicates a shutdown request.
+/// used by `axum::serve(...).with_graceful_shutdown`, updated mindmap is saved after in-flight requests finished
 pub async fn wait_for_signal() {
     //println!("start waiting signal ...");
     wait_for_signal_impl().await;
-    //println!("do something ...");
-    let data = DATA.read().unwrap();
-    data.save_mindmap();
+    event!(Level::INFO, "shutting down, waiting for in-flight requests");
 }
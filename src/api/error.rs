@@ -1,7 +1,17 @@
 use std::io;
 use std::num::ParseIntError;
 
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
 use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::etag::etag;
 
 /// https://github.com/dtolnay/thiserror
 /// https://crates.io/crates/thiserror
@@ -18,10 +28,46 @@ pub enum MyError {
     #[error("Error - fs::create_dir_all {dir_name}: {error}")]
     CreateDirAllError{dir_name: String, error: io::Error},
 
+    // write file error
+    #[error("Error - write {file}: {error}")]
+    WriteFileError{file: String, error: io::Error},
+
+    // parse json file error
+    #[error("Error - parse json {file}: {error}")]
+    ParseJsonError{file: String, error: serde_json::Error},
+
     // string to int error
     #[error("Error - parse {from} -> {to}: {error}")]
     ParseStringError{from: String, to: String, error: ParseIntError},
 
+    // invalid mindmap id
+    #[error("Error - invalid mindmap id {id:?}, must be a uuid like f66bedbd-9972-4ec3-9a30-9510d4fffe1c")]
+    InvalidMapIdError{id: String},
+
+    // invalid mindmap data
+    #[error("Error - {reason}")]
+    ValidationError{reason: String},
+
+    // mindmap, revision or route not found
+    #[error("Error - {what} not found")]
+    NotFoundError{what: String},
+
+    // request conflicts with the current state
+    #[error("Error - {reason}")]
+    ConflictError{reason: String},
+
+    // `If-Match` revision is stale, mindmap has been changed by others
+    #[error("Error - {uuid} has been changed elsewhere, current revision is {revision}")]
+    RevisionConflictError{uuid: String, revision: u64},
+
+    // save mindmap to output path error
+    #[error("Error - save {uuid}: {reason}")]
+    StorageError{uuid: String, reason: String},
+
+    // render png or pdf error
+    #[error("Error - render: {reason}")]
+    RenderError{reason: String},
+
     // para error
     #[error("Error - {para}")]
     ParaError{para: String},
@@ -29,4 +75,57 @@ pub enum MyError {
     // io::Error
     #[error("I/O error occurred")]
     IoError(#[from] io::Error),
+
+    // zip::result::ZipError
+    #[error("Error - zip: {0}")]
+    ZipError(#[from] zip::result::ZipError),
+}
+
+impl MyError {
+    /// http status code of this error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            MyError::FileNotExistError{..} | MyError::NotFoundError{..} => StatusCode::NOT_FOUND,
+            MyError::InvalidMapIdError{..} | MyError::ParseStringError{..} | MyError::ParaError{..} => StatusCode::BAD_REQUEST,
+            MyError::ValidationError{..} => StatusCode::UNPROCESSABLE_ENTITY,
+            MyError::ConflictError{..} | MyError::RevisionConflictError{..} => StatusCode::CONFLICT,
+            MyError::CreateDirAllError{..} | MyError::WriteFileError{..} | MyError::ParseJsonError{..} | MyError::StorageError{..} | MyError::RenderError{..} | MyError::IoError(_) | MyError::ZipError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// response with status code and json body, e.g. `{"error": "Error - mindmap f66bedbd-9972-4ec3-9a30-9510d4fffe1c not found"}`
+impl IntoResponse for MyError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            event!(Level::ERROR, "{}", self);
+        }
+        match self {
+            // also return current revision, so the page can tell the user
+            MyError::RevisionConflictError{revision, ..} => (status, [(header::ETAG, etag(revision))], Json(json!({"error": self.to_string(), "revision": revision}))).into_response(),
+            _ => (status, Json(json!({"error": self.to_string()}))).into_response(),
+        }
+    }
+}
+
+/// invalid path parameter, e.g. `/download/../../etc`
+impl From<PathRejection> for MyError {
+    fn from(rejection: PathRejection) -> Self {
+        MyError::ParaError{para: rejection.body_text()}
+    }
+}
+
+/// invalid query string, e.g. `/update?uuid=../../etc`
+impl From<QueryRejection> for MyError {
+    fn from(rejection: QueryRejection) -> Self {
+        MyError::ParaError{para: rejection.body_text()}
+    }
+}
+
+/// invalid json body, e.g. `PATCH /api/v1/mindmaps/:uuid` with `{"label": 1}`
+impl From<JsonRejection> for MyError {
+    fn from(rejection: JsonRejection) -> Self {
+        MyError::ValidationError{reason: rejection.body_text()}
+    }
 }
@@ -0,0 +1,130 @@
+/// escape text for html content and single or double quoted attribute, e.g. `<option>` text and `placeholder='...'`
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// escape text for a single quoted javascript string inside `<script>`, e.g. `JSON.parse('...')`
+/// `<` is escaped so `</script>` and `<!--` can not close or break the script block
+pub fn escape_js_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '<' => out.push_str("\\x3C"),
+            '>' => out.push_str("\\x3E"),
+            '\u{2028}' => out.push_str("\\u2028"), // line separator, invalid in js string before ES2019
+            '\u{2029}' => out.push_str("\\u2029"), // paragraph separator
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\x{:02X}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// percent-encode text for a url query value, same as javascript `encodeURIComponent`
+pub fn encode_uri_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// escape text for xml content and double quoted attribute, line breaks are kept as `&#10;` so attributes do not lose them
+pub fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' => out.push_str("&#9;"),
+            c if (c as u32) < 0x20 => {}, // not allowed in xml 1.0
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// escape text for a double quoted Graphviz DOT string, line breaks become centered `\n`
+pub fn escape_dot(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => {},
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{editor_page, map_id::MapId};
+
+    /// text with every character that could break a page, `\u{2028}` is a line separator
+    const NASTY: &str = "it's a \\ path\nnext</script><script>alert(1)</script>\u{2028}思维导图";
+
+    #[test]
+    fn js_string() {
+        assert_eq!(escape_js_string("it's"), "it\\'s");
+        assert_eq!(escape_js_string("a\\b"), "a\\\\b");
+        assert_eq!(escape_js_string("a\nb\r"), "a\\nb\\r");
+        assert_eq!(escape_js_string("</script>"), "\\x3C/script\\x3E");
+        assert_eq!(escape_js_string("a\u{2028}b\u{2029}"), "a\\u2028b\\u2029");
+        assert_eq!(escape_js_string("思维导图"), "思维导图");
+        assert_eq!(escape_js_string("\u{1}"), "\\x01");
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(escape_html("it's"), "it&#39;s");
+        assert_eq!(escape_html("a\\b\nc"), "a\\b\nc");
+        assert_eq!(escape_html("</script>"), "&lt;/script&gt;");
+        assert_eq!(escape_html("\"&\""), "&quot;&amp;&quot;");
+        assert_eq!(escape_html("\u{2028}思维导图"), "\u{2028}思维导图");
+    }
+
+    #[test]
+    fn editor_page_escaped() {
+        let uuid = MapId::new();
+        let content = format!("{{\"nodeData\":{{\"id\":\"root\",\"topic\":\"{}\"}}}}", NASTY.replace('\\', "\\\\").replace('\n', "\\n"));
+        let html = editor_page(&uuid, "", Some(&content), Some(NASTY), Some(3), Some(NASTY));
+        let data = html.split("JSON.parse('").nth(1).and_then(|s| s.split("')").next()).unwrap();
+        assert_eq!(data, escape_js_string(&content));
+        assert!(data.contains("it\\'s a \\\\\\\\ path\\\\nnext\\x3C/script\\x3E"));
+        assert!(data.contains("\\u2028思维导图"));
+        assert!(!data.contains('\u{2028}'));
+        assert!(html.contains(&format!("let focus_node = '{}';", escape_js_string(NASTY))));
+        assert!(html.contains(&format!("placeholder='{}'>", escape_html(NASTY))));
+        assert!(html.contains("let revision = '3';"));
+        // only the script blocks of the page itself are closed
+        assert_eq!(html.matches("</script>").count(), editor_page(&uuid, "", None, None, None, None).matches("</script>").count());
+    }
+}
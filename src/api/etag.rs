@@ -0,0 +1,23 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+
+/// strong etag of mindmap revision, e.g. `"5"`
+pub fn etag(revision: u64) -> HeaderValue {
+    format!("\"{}\"", revision).parse().unwrap()
+}
+
+/// check `If-Match` header against the current revision, 0 means mindmap not exist
+/// return true if there is no `If-Match`, it is `*` for an existing mindmap, or any listed etag equals the current revision
+pub fn if_match(headers: &HeaderMap, revision: u64) -> bool {
+    let value = match headers.get(header::IF_MATCH).and_then(|v| v.to_str().ok()) {
+        Some(v) => v.trim(),
+        None => return true,
+    };
+    if value == "*" {
+        return revision > 0
+    }
+    value.split(',').any(|tag| {
+        let tag = tag.trim();
+        let tag = tag.strip_prefix("W/").unwrap_or(tag); // weak etag is also accepted, it is the same revision
+        tag.trim_matches('"').parse::<u64>().is_ok_and(|r| r == revision)
+    })
+}
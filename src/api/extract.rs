@@ -0,0 +1,29 @@
+use axum::{
+    extract::{FromRequest, FromRequestParts},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::error::MyError;
+
+/// same as `axum::extract::Path`, but reject with `MyError` json body
+/// https://github.com/tokio-rs/axum/blob/main/examples/customize-extractor-error/src/derive_from_request.rs
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(MyError))]
+pub struct Path<T>(pub T);
+
+/// same as `axum::extract::Query`, but reject with `MyError` json body
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(MyError))]
+pub struct Query<T>(pub T);
+
+/// same as `axum::Json`, but reject with `MyError` json body, also usable as response
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(MyError))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
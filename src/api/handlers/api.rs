@@ -0,0 +1,160 @@
+use std::time::SystemTime;
+
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    error::MyError,
+    etag::{etag, if_match},
+    extract::{Json, Path, Query},
+    map_id::MapId,
+    model::MindMapData,
+    MapSort,
+};
+
+/// one mindmap in `GET /api/v1/mindmaps`
+#[derive(Serialize)]
+pub struct MindMapItem {
+    uuid:       MapId,
+    label:      Option<String>,
+    root_topic: String,
+    created:    Option<String>, // RFC 3339 local time
+    modified:   Option<String>, // RFC 3339 local time, None if never saved
+    size:       usize, // bytes of mindmap data
+    node_count: usize,
+    revision:   u64,
+    archived:   bool,
+}
+
+/// returned by create, replace and patch
+#[derive(Serialize)]
+pub struct RevisionBody {
+    uuid:     MapId,
+    revision: u64,
+}
+
+/// `GET /api/v1/mindmaps` query parameters
+#[derive(Deserialize)]
+pub struct ListParams {
+    #[serde(default)]
+    sort: MapSort, // modified, created, label, topic
+    q:    Option<String>, // filter by uuid, label or root topic
+}
+
+/// `POST /api/v1/mindmaps` query parameters
+#[derive(Deserialize)]
+pub struct CreateParams {
+    label: Option<String>,
+}
+
+/// `PATCH /api/v1/mindmaps/:uuid` body, null or empty label removes it
+#[derive(Deserialize)]
+pub struct PatchBody {
+    label: Option<String>,
+}
+
+/// format modified time as RFC 3339 local time, e.g. `2025-10-31T12:30:59.123+08:00`
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).to_rfc3339()
+}
+
+/// 409 if `If-Match` is given and not the current revision, 404 if mindmap not exist
+pub fn check_revision(headers: &HeaderMap, uuid: &MapId, current: Option<u64>) -> Result<(), MyError> {
+    match current {
+        Some(r) if if_match(headers, r) => Ok(()),
+        Some(r) => Err(MyError::RevisionConflictError{uuid: uuid.to_string(), revision: r}),
+        None => Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    }
+}
+
+/// Handler for `/api/v1/mindmaps` GET
+/// list all mindmap, archived mindmap is included, `?sort=` and `?q=` to sort and filter
+pub async fn api_list(Query(params): Query<ListParams>, uri: OriginalUri) -> Result<Json<Vec<MindMapItem>>, MyError> {
+    event!(Level::INFO, "GET `{}`", uri.path());
+    let list = DATA.read().unwrap().list_mindmaps(params.sort, params.q.as_deref());
+    Ok(Json(list.into_iter().map(|m| MindMapItem{
+        uuid:       m.uuid,
+        label:      m.label,
+        root_topic: m.root_topic,
+        created:    m.created.map(format_time),
+        modified:   m.modified.map(format_time),
+        size:       m.size,
+        node_count: m.node_count,
+        revision:   m.revision,
+        archived:   m.archived,
+    }).collect()))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid` GET
+/// return raw mind-elixir json, current revision as `ETag`
+pub async fn api_get(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<impl IntoResponse, MyError> {
+    let mut data = DATA.write().unwrap();
+    let (content, _) = match data.load_mindmap(&uuid) {
+        Some(c) => c,
+        None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    };
+    let revision = data.revision(&uuid).unwrap_or(1);
+    event!(Level::INFO, "GET `{}`", uri.path());
+    Ok(([(header::CONTENT_TYPE, "application/json".parse().unwrap()), (header::ETAG, etag(revision))], content))
+}
+
+/// Handler for `/api/v1/mindmaps` POST
+/// create mindmap from mind-elixir json body with a new uuid, 201 with `Location`
+pub async fn api_create(Query(params): Query<CreateParams>, uri: OriginalUri, body: String) -> Result<impl IntoResponse, MyError> {
+    MindMapData::parse(&body)?;
+    let uuid = MapId::new();
+    let mut data = DATA.write().unwrap();
+    let revision = data.update_loaded_mindmap(uuid.clone(), body, params.label.filter(|l| !l.is_empty()));
+    data.write_through(&uuid)?;
+    event!(Level::INFO, "POST `{}`, create {}", uri.path(), uuid);
+    Ok((
+        StatusCode::CREATED,
+        [(header::LOCATION, format!("/api/v1/mindmaps/{}", uuid).parse().unwrap()), (header::ETAG, etag(revision))],
+        Json(RevisionBody{uuid, revision}),
+    ))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid` PUT
+/// replace mindmap data, label is kept, `If-Match` is checked if given
+pub async fn api_replace(Path(uuid): Path<MapId>, uri: OriginalUri, headers: HeaderMap, body: String) -> Result<impl IntoResponse, MyError> {
+    MindMapData::parse(&body)?;
+    let mut data = DATA.write().unwrap();
+    let current = data.revision(&uuid);
+    check_revision(&headers, &uuid, current)?;
+    let label = data.label(&uuid);
+    let revision = data.update_loaded_mindmap(uuid.clone(), body, label);
+    data.write_through(&uuid)?;
+    event!(Level::INFO, "PUT `{}`, revision {}", uri.path(), revision);
+    Ok(([(header::ETAG, etag(revision))], Json(RevisionBody{uuid, revision})))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid` PATCH
+/// change label, `If-Match` is checked if given
+pub async fn api_patch(Path(uuid): Path<MapId>, uri: OriginalUri, headers: HeaderMap, Json(patch): Json<PatchBody>) -> Result<impl IntoResponse, MyError> {
+    let mut data = DATA.write().unwrap();
+    let current = data.revision(&uuid);
+    check_revision(&headers, &uuid, current)?;
+    let content = match data.load_mindmap(&uuid) {
+        Some((c, _)) => c,
+        None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    };
+    let revision = data.update_loaded_mindmap(uuid.clone(), content, patch.label.filter(|l| !l.is_empty()));
+    data.write_through(&uuid)?;
+    event!(Level::INFO, "PATCH `{}`, revision {}", uri.path(), revision);
+    Ok(([(header::ETAG, etag(revision))], Json(RevisionBody{uuid, revision})))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid` DELETE
+/// move mindmap to trash, same as `DELETE /mindmap/:uuid`
+pub async fn api_delete(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    DATA.write().unwrap().delete_mindmap(&uuid)?;
+    event!(Level::INFO, "DELETE `{}`, {} moved to trash", uri.path(), uuid);
+    Ok(StatusCode::NO_CONTENT)
+}
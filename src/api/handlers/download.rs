@@ -2,33 +2,59 @@ use std::io::Error;
 
 use axum::{
     body::Body,
-    extract::{OriginalUri, Path},
+    extract::OriginalUri,
     http::{header, HeaderMap},
 };
+use serde::Deserialize;
 use tracing::{event, Level};
 
-use crate::DATA;
+use crate::{
+    DATA,
+    convert::{export_unlocked, ExportOptions, Format},
+    error::MyError,
+    etag::etag,
+    extract::{Path, Query},
+    map_id::MapId,
+};
+
+/// `/download/:uuid` query parameters
+#[derive(Deserialize)]
+pub struct DownloadParams {
+    format: Option<Format>, // default: html
+    depth:  Option<usize>, // markdown heading depth, default: 2
+}
 
 /// Handler for `/download/:uuid` GET
-/// download mindmap html file
-pub async fn download(Path(uuid): Path<String>, uri: OriginalUri) -> (HeaderMap, Body) {
-    // prepeare header
-    let mut headers = HeaderMap::new();
-    headers.insert(header::CONTENT_TYPE, "text/html; charset=utf-8".parse().unwrap()); // value is `header::HeaderValue`, create: `HeaderValue::from_str("hello").unwrap()`
+/// download mindmap html file, or other format by `?format=`, e.g. `?format=md`
+pub async fn download(Path(uuid): Path<MapId>, Query(params): Query<DownloadParams>, uri: OriginalUri) -> Result<(HeaderMap, Body), MyError> {
+    let format = params.format.unwrap_or(Format::Html);
+    if format != Format::Html {
+        let mut options = ExportOptions::default();
+        if let Some(d) = params.depth {
+            options.heading_depth = d;
+        }
+        let (content, revision) = export_unlocked(&uuid, format, options).await?;
+        event!(Level::INFO, "GET `{}`, download {}.{}", uri.path(), uuid, format.extension());
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, format.content_type().parse().unwrap());
+        headers.insert(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", uuid, format.extension()).parse().unwrap());
+        headers.insert(header::ETAG, etag(revision));
+        return Ok((headers, Body::from(content)))
+    }
     // get content
-    let data = DATA.read().unwrap();
-    let html_str = match data.html_content(&uuid) {
-        Some(content) => {
-            event!(Level::INFO, "GET `{}`, download {}.html", uri.path(), uuid);
-            headers.insert(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.html\"", uuid).parse().unwrap());
-            content
-        },
+    let (html_str, revision) = match DATA.write().unwrap().html_content(&uuid) {
+        Some(content) => content,
         None => {
             event!(Level::INFO, "GET `{}`, unable to retrieve the relevant mindmap due to uuid {} not found", uri.path(), uuid);
-            headers.insert(header::CONTENT_DISPOSITION, "attachment; filename=\"uuid_not_found.txt\"".parse().unwrap());
-            format!("Unable to retrieve the relevant mindmap due to uuid {} not found.", uuid)
+            return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
         },
     };
+    event!(Level::INFO, "GET `{}`, download {}.html", uri.path(), uuid);
+    // prepeare header
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "text/html; charset=utf-8".parse().unwrap()); // value is `header::HeaderValue`, create: `HeaderValue::from_str("hello").unwrap()`
+    headers.insert(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.html\"", uuid).parse().unwrap());
+    headers.insert(header::ETAG, etag(revision));
     // stream content
     let stream = async_stream::stream! {
         // https://users.rust-lang.org/t/solved-how-to-split-string-into-multiple-sub-strings-with-given-length/10542/12
@@ -44,5 +70,5 @@ pub async fn download(Path(uuid): Path<String>, uri: OriginalUri) -> (HeaderMap,
     };
     // convert the `Stream` into an `axum::body::Body`
     let body = Body::from_stream(stream); // v0.7 change `StreamBody::new(stream);` to `Body::from_stream(stream)`
-    (headers, body)
+    Ok((headers, body))
 }
@@ -1,7 +1,7 @@
-use axum::http::StatusCode;
+use crate::error::MyError;
 
 /// Handler for any request that fails to match the router routes
-pub async fn fallback(uri: axum::http::Uri) -> impl axum::response::IntoResponse {
+pub async fn fallback(uri: axum::http::Uri) -> MyError {
     //event!(Level::INFO, "fails to match any route");
-    (StatusCode::NOT_FOUND, format!("No route {}", uri))
+    MyError::NotFoundError{what: format!("route {}", uri)}
 }
@@ -0,0 +1,72 @@
+use axum::{
+    extract::OriginalUri,
+    response::{Html, Redirect},
+};
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    editor_page,
+    simple_page,
+    error::MyError,
+    extract::Path,
+    history::{format_rev, list_revisions, read_revision},
+    map_id::MapId,
+};
+
+/// Handler for `/history/:uuid` GET
+/// list all revisions of the mindmap
+pub async fn history(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<Html<String>, MyError> {
+    if !DATA.read().unwrap().contains(&uuid) {
+        return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
+    }
+    event!(Level::INFO, "GET `{}`, list history of {}", uri.path(), uuid);
+    let revs = list_revisions(&uuid);
+    let rows: Vec<String> = revs.iter().map(|(rev, size)| format!(
+        "      <tr><td><a href='/history/{uuid}/{rev}'>{time}</a></td><td>{size} bytes</td><td><form method='post' action='/history/{uuid}/{rev}/restore'><button type='submit'>restore</button></form></td></tr>",
+        uuid = uuid,
+        rev = rev,
+        time = format_rev(*rev),
+        size = size,
+    )).collect();
+    let body = format!(
+        "    <h3>history of <a href='/previous?uuid={uuid}'>{uuid}</a>, {num} revisions</h3>\n    <table>\n      <tr><th>time</th><th>size</th><th></th></tr>\n{rows}\n    </table>",
+        uuid = uuid,
+        num = revs.len(),
+        rows = rows.join("\n"),
+    );
+    Ok(simple_page("MindMap history", &body).into())
+}
+
+/// Handler for `/history/:uuid/:rev` GET
+/// view one revision in the editor page, record is replaced by restore, so only restore makes it current
+pub async fn history_view(Path((uuid, rev)): Path<(MapId, u64)>, uri: OriginalUri) -> Result<Html<String>, MyError> {
+    let data = DATA.read().unwrap();
+    if !data.contains(&uuid) {
+        return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
+    }
+    let content = match read_revision(&uuid, rev) {
+        Ok(c) => c,
+        Err(_) => return Err(MyError::NotFoundError{what: format!("revision {} of {}", rev, uuid)}),
+    };
+    event!(Level::INFO, "GET `{}`, view {} revision {}", uri.path(), uuid, rev);
+    let label = data.label(&uuid);
+    let html = editor_page(&uuid, &data.html_pulldown(&uuid), Some(&content), label.as_deref(), None, None)
+        .replace("let history_rev = '';", &format!("let history_rev = '{}';", rev));
+    Ok(html.into())
+}
+
+/// Handler for `/history/:uuid/:rev/restore` POST
+/// make the revision current, then redirect to `/previous?uuid=`
+pub async fn history_restore(Path((uuid, rev)): Path<(MapId, u64)>, uri: OriginalUri) -> Result<Redirect, MyError> {
+    let mut data = DATA.write().unwrap();
+    if !data.contains(&uuid) {
+        return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
+    }
+    if read_revision(&uuid, rev).is_err() {
+        return Err(MyError::NotFoundError{what: format!("revision {} of {}", rev, uuid)})
+    }
+    data.restore_revision(&uuid, rev)?;
+    event!(Level::INFO, "POST `{}`, {} restored to revision {}", uri.path(), uuid, rev);
+    Ok(Redirect::to(&format!("/previous?uuid={}", uuid)))
+}
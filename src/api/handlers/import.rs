@@ -0,0 +1,40 @@
+use axum::{
+    body::Bytes,
+    extract::OriginalUri,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    convert::{import, Format},
+    error::MyError,
+    extract::{Json, Query},
+};
+
+/// `/api/v1/import` query parameters
+#[derive(Deserialize)]
+pub struct ImportParams {
+    format: Format,
+    label:  Option<String>,
+}
+
+/// Handler for `/api/v1/import` POST
+/// create new mindmaps from the body, then redirect to `/previous?uuid=` of the first one
+/// `uuids` lists all created mindmaps, e.g. one for each XMind sheet
+pub async fn api_import(Query(params): Query<ImportParams>, uri: OriginalUri, body: Bytes) -> Result<impl IntoResponse, MyError> {
+    let mut data = DATA.write().unwrap();
+    let uuids = import(&mut data, &body, params.format, params.label.filter(|l| !l.is_empty()))?;
+    for uuid in &uuids {
+        data.write_through(uuid)?;
+        event!(Level::INFO, "POST `{}`, import {} as {}", uri.path(), params.format, uuid);
+    }
+    Ok((
+        StatusCode::SEE_OTHER,
+        [(header::LOCATION, format!("/previous?uuid={}", uuids[0]))], // import returns at least one uuid
+        Json(json!({"uuid": uuids[0], "uuids": uuids})),
+    ))
+}
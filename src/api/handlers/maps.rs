@@ -0,0 +1,75 @@
+use axum::{
+    extract::OriginalUri,
+    response::Html,
+};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    simple_page,
+    error::MyError,
+    escape::{encode_uri_component, escape_html},
+    extract::Query,
+    history::format_time,
+    MapSort,
+};
+
+/// sort options shown on the page, (sort, value of `?sort=`)
+const SORTS: [(MapSort, &str); 4] = [
+    (MapSort::Modified, "modified"),
+    (MapSort::Created, "created"),
+    (MapSort::Label, "label"),
+    (MapSort::Topic, "topic"),
+];
+
+/// `/maps` query parameters
+#[derive(Deserialize)]
+pub struct MapsParams {
+    #[serde(default)]
+    sort: MapSort,
+    #[serde(default)]
+    q:    String, // filter by uuid, label or root topic
+}
+
+/// human readable size, e.g. `1.5 KB`
+fn format_size(size: usize) -> String {
+    if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", size as f64 / 1024.0 / 1024.0)
+    }
+}
+
+/// Handler for `/maps` GET
+/// list all mindmap with label, root topic, modification time and size
+pub async fn maps(Query(params): Query<MapsParams>, uri: OriginalUri) -> Result<Html<String>, MyError> {
+    let list = DATA.read().unwrap().list_mindmaps(params.sort, Some(&params.q));
+    event!(Level::INFO, "GET `{}`, {} mindmap", uri.path(), list.len());
+    let rows: Vec<String> = list.iter().map(|m| format!(
+        "      <tr><td><a href='/previous?uuid={uuid}'>{label}</a>{archived}</td><td>{topic}</td><td>{modified}</td><td>{size}</td><td>{nodes}</td><td><a href='/history/{uuid}'>history</a></td></tr>",
+        uuid = m.uuid,
+        label = escape_html(m.label.as_deref().unwrap_or(m.uuid.as_str())),
+        archived = if m.archived { " (archived)" } else { "" },
+        topic = escape_html(&m.root_topic),
+        modified = m.modified.map(format_time).unwrap_or_default(),
+        size = format_size(m.size),
+        nodes = m.node_count,
+    )).collect();
+    let sorts: Vec<String> = SORTS.iter().map(|(sort, value)| format!(
+        "<option value='{value}'{selected}>{value}</option>",
+        value = value,
+        selected = if *sort == params.sort { " selected" } else { "" },
+    )).collect();
+    let body = format!(
+        "    <form method='get' action='/maps'><input type='text' name='q' value='{q}' size='30' placeholder='filter' /> <select name='sort'>{sorts}</select> <button type='submit'>list</button> <a href='/search?q={q_url}'>search nodes</a></form>\n    <h3>{num} mindmaps</h3>\n    <table>\n      <tr><th>label</th><th>root topic</th><th>modified</th><th>size</th><th>nodes</th><th></th></tr>\n{rows}\n    </table>",
+        q = escape_html(&params.q),
+        q_url = escape_html(&encode_uri_component(&params.q)),
+        sorts = sorts.join(""),
+        num = list.len(),
+        rows = rows.join("\n"),
+    );
+    Ok(simple_page("MindMap list", &body).into())
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::OriginalUri,
+    http::StatusCode,
+};
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    error::MyError,
+    extract::Path,
+    map_id::MapId,
+};
+
+/// Handler for `/mindmap/:uuid` DELETE
+/// move mindmap to trash
+pub async fn delete_mindmap(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    DATA.write().unwrap().delete_mindmap(&uuid)?;
+    event!(Level::INFO, "DELETE `{}`, {} moved to trash", uri.path(), uuid);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `/mindmap/:uuid/archive` POST
+/// hide mindmap from the pulldown without deleting it
+pub async fn archive(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    DATA.write().unwrap().set_archived(&uuid, true)?;
+    event!(Level::INFO, "POST `{}`", uri.path());
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `/mindmap/:uuid/unarchive` POST
+/// show archived mindmap in the pulldown again
+pub async fn unarchive(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    DATA.write().unwrap().set_archived(&uuid, false)?;
+    event!(Level::INFO, "POST `{}`", uri.path());
+    Ok(StatusCode::NO_CONTENT)
+}
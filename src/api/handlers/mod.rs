@@ -1,5 +1,14 @@
 pub mod index; // GET `/`
 pub mod update; // GET `/update`
-pub mod previous; // GET `/previous`
+pub mod previous; // GET `/previous`, GET `/m/:uuid/:node_id`
 pub mod download; // GET `/download/:uuid`
+pub mod history; // GET `/history/:uuid`, GET `/history/:uuid/:rev`, POST `/history/:uuid/:rev/restore`
+pub mod mindmap; // DELETE `/mindmap/:uuid`, POST `/mindmap/:uuid/archive`, POST `/mindmap/:uuid/unarchive`
+pub mod trash; // GET `/trash`, POST `/trash/:uuid/restore`, DELETE `/trash/:uuid`
+pub mod api; // GET/POST `/api/v1/mindmaps`, GET/PUT/PATCH/DELETE `/api/v1/mindmaps/:uuid`
+pub mod nodes; // GET/PATCH `/api/v1/mindmaps/:uuid/nodes/:node_id`, POST `.../children`, POST `.../move`
+pub mod search; // GET `/search`, GET `/api/v1/search`
+pub mod maps; // GET `/maps`
+pub mod import; // POST `/api/v1/import`
+pub mod render; // GET `/render/:uuid.svg`, `.png` or `.pdf`
 pub mod fallback; // `NOT_FOUND`
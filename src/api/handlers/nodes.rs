@@ -0,0 +1,144 @@
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    error::MyError,
+    etag::etag,
+    extract::{Json, Path},
+    map_id::MapId,
+    model::{new_node_id, MindMapData, NodeObj, NodeStyle, Tag},
+};
+
+use super::api::check_revision;
+
+/// `PATCH /api/v1/mindmaps/:uuid/nodes/:node_id` body, only given fields are changed
+/// empty `hyperLink` removes the link, empty `style` object removes the style
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NodePatch {
+    topic:      Option<String>,
+    tags:       Option<Vec<Tag>>,
+    icons:      Option<Vec<String>>,
+    style:      Option<NodeStyle>,
+    hyper_link: Option<String>,
+}
+
+/// `POST /api/v1/mindmaps/:uuid/nodes/:node_id/move` body
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MoveBody {
+    parent: String,
+    index:  Option<usize>, // position in the new parent's children, append if not given
+}
+
+/// load and parse mindmap, apply `edit`, then store it as a new revision
+/// `edit` returns the node id to respond with, return (new revision, that node)
+fn edit_mindmap<F>(uuid: &MapId, headers: &HeaderMap, edit: F) -> Result<(u64, NodeObj), MyError>
+where
+    F: FnOnce(&mut MindMapData) -> Result<String, MyError>,
+{
+    let mut data = DATA.write().unwrap();
+    let current = data.revision(uuid);
+    check_revision(headers, uuid, current)?;
+    let (content, label) = match data.load_mindmap(uuid) {
+        Some(c) => c,
+        None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    };
+    let mut mindmap = MindMapData::parse(&content)?;
+    let node_id = edit(&mut mindmap)?;
+    let node = mindmap.node_data.find(&node_id).cloned().unwrap(); // edit returns an existing node id
+    let revision = data.update_loaded_mindmap(uuid.clone(), mindmap.to_json(), label);
+    data.write_through(uuid)?;
+    Ok((revision, node))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid/nodes/:node_id` GET
+/// return one node and its descendants
+pub async fn node_get(Path((uuid, node_id)): Path<(MapId, String)>, uri: OriginalUri) -> Result<impl IntoResponse, MyError> {
+    let mut data = DATA.write().unwrap();
+    let content = match data.load_mindmap(&uuid) {
+        Some((c, _)) => c,
+        None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+    };
+    let revision = data.revision(&uuid).unwrap_or(1);
+    let mindmap = MindMapData::parse(&content)?;
+    match mindmap.node_data.find(&node_id) {
+        Some(node) => {
+            event!(Level::INFO, "GET `{}`", uri.path());
+            Ok(([(header::ETAG, etag(revision))], Json(node.clone())))
+        },
+        None => Err(MyError::NotFoundError{what: format!("node {:?} in {}", node_id, uuid)}),
+    }
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid/nodes/:node_id/children` POST
+/// append a child node, body is a node object, `id` is generated if missing, optional `index` is the position in children
+pub async fn node_add_child(Path((uuid, node_id)): Path<(MapId, String)>, uri: OriginalUri, headers: HeaderMap, Json(mut body): Json<Map<String, Value>>) -> Result<impl IntoResponse, MyError> {
+    let index = match body.remove("index") {
+        Some(Value::Number(n)) if n.as_u64().is_some() => Some(n.as_u64().unwrap() as usize),
+        Some(Value::Null) | None => None,
+        Some(v) => return Err(MyError::ValidationError{reason: format!("index must be a non-negative integer, got {}", v)}),
+    };
+    if body.get("id").and_then(|id| id.as_str()).is_none_or(|id| id.is_empty()) {
+        body.insert("id".to_string(), Value::String(new_node_id()));
+    }
+    let child: NodeObj = match serde_json::from_value(Value::Object(body)) {
+        Ok(c) => c,
+        Err(e) => return Err(MyError::ValidationError{reason: format!("invalid node: {}", e)}),
+    };
+    let (revision, node) = edit_mindmap(&uuid, &headers, |mindmap| {
+        let id = child.id.clone();
+        mindmap.add_child(&node_id, child, index)?;
+        Ok(id)
+    })?;
+    event!(Level::INFO, "POST `{}`, add node {:?}, revision {}", uri.path(), node.id, revision);
+    Ok((StatusCode::CREATED, [(header::ETAG, etag(revision))], Json(node)))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid/nodes/:node_id` PATCH
+/// change topic, tags, icons, style or hyperLink of one node
+pub async fn node_patch(Path((uuid, node_id)): Path<(MapId, String)>, uri: OriginalUri, headers: HeaderMap, Json(patch): Json<NodePatch>) -> Result<impl IntoResponse, MyError> {
+    let (revision, node) = edit_mindmap(&uuid, &headers, |mindmap| {
+        let node = match mindmap.node_data.find_mut(&node_id) {
+            Some(n) => n,
+            None => return Err(MyError::NotFoundError{what: format!("node {:?} in {}", node_id, uuid)}),
+        };
+        if let Some(topic) = patch.topic {
+            node.topic = topic;
+        }
+        if let Some(tags) = patch.tags {
+            node.tags = tags;
+        }
+        if let Some(icons) = patch.icons {
+            node.icons = icons;
+        }
+        if let Some(style) = patch.style {
+            let empty = style.font_size.is_none() && style.color.is_none() && style.background.is_none() && style.font_weight.is_none() && style.extra.is_empty();
+            node.style = if empty { None } else { Some(style) };
+        }
+        if let Some(link) = patch.hyper_link {
+            node.hyper_link = if link.is_empty() { None } else { Some(link) };
+        }
+        Ok(node_id.clone())
+    })?;
+    event!(Level::INFO, "PATCH `{}`, revision {}", uri.path(), revision);
+    Ok(([(header::ETAG, etag(revision))], Json(node)))
+}
+
+/// Handler for `/api/v1/mindmaps/:uuid/nodes/:node_id/move` POST
+/// move node with its descendants under another parent
+pub async fn node_move(Path((uuid, node_id)): Path<(MapId, String)>, uri: OriginalUri, headers: HeaderMap, Json(body): Json<MoveBody>) -> Result<impl IntoResponse, MyError> {
+    let (revision, node) = edit_mindmap(&uuid, &headers, |mindmap| {
+        mindmap.move_node(&node_id, &body.parent, body.index)?;
+        Ok(node_id.clone())
+    })?;
+    event!(Level::INFO, "POST `{}`, move to {:?}, revision {}", uri.path(), body.parent, revision);
+    Ok(([(header::ETAG, etag(revision))], Json(node)))
+}
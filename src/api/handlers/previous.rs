@@ -1,69 +1,81 @@
-use std::collections::HashMap;
-
 use axum::{
-    extract::{Query, OriginalUri},
+    extract::OriginalUri,
+    http::{header, HeaderMap, StatusCode},
     response::Html,
 };
+use serde::Deserialize;
 use tracing::{event, Level};
-use uuid::Uuid;
 
 use crate::{
-    DEFAULT_PAGE,
-    INDEX,
-    KATEX,
     DATA,
-    parse_paras::PARAS,
+    editor_page,
+    etag::etag,
+    extract::{Path, Query},
+    map_id::MapId,
+    model::MindMapData,
 };
 
-/// Handler for `/previous` GET
-pub async fn previous(Query(params): Query<HashMap<String, String>>, uri: OriginalUri) -> Html<String> {
-    let (content_label, pulldown, uuid): (Option<(String, Option<String>)>, String, String) = match params.get("uuid") {
+/// `/previous` query parameters, invalid uuid is rejected with 400
+#[derive(Deserialize)]
+pub struct PreviousParams {
+    uuid: Option<MapId>,
+    node: Option<String>, // node id to select and center on load
+}
+
+/// prepare editor page of uuid mindmap, create new mindmap if uuid is None or not exist
+/// the current revision is embedded in the page and returned as `ETag`
+/// if `node` not exist in the stored mindmap, the page shows a notice and the status is 404
+fn previous_page(uuid: Option<MapId>, node: Option<String>, uri: &OriginalUri) -> (StatusCode, HeaderMap, Html<String>) {
+    let (content_label, pulldown, uuid, revision) = match uuid {
         Some(uuid) => {
             let mut data = DATA.write().unwrap();
-            let (content_label, pulldown) = data.get_local_mindmap(uuid);
+            let (content_label, pulldown) = data.get_local_mindmap(&uuid);
+            let revision = data.revision(&uuid);
             if content_label.is_none() {
                 event!(Level::INFO, "GET `{}`, redirect to {}, but no such uuid in local, create new mindmap", uri.path(), uuid);
             } else {
                 event!(Level::INFO, "GET `{}`, redirect to {}", uri.path(), uuid);
             }
-            (content_label, pulldown, uuid.clone())
+            (content_label, pulldown, uuid, revision)
         },
         None => {
-            let uuid = Uuid::new_v4().to_string();
+            let uuid = MapId::new();
             event!(Level::INFO, "GET `{}`, missing uuid when redirect, create new uuid {}", uri.path(), &uuid);
             let data = DATA.read().unwrap();
             let pulldown = data.html_pulldown(&uuid);
-            (None, pulldown, uuid)
+            (None, pulldown, uuid, None)
         },
     };
-    match content_label {
-        Some((content, label)) => {
-            let mut html = DEFAULT_PAGE
-                .replace("127.0.0.1:8081", &format!("{}:{}", &PARAS.addr_str, PARAS.port))
-                .replace("download/mindmap", &format!("download/{}", &uuid))
-                .replace("<option value='mindmap' selected>mindmap</option>", &format!("{}\n", pulldown))
-                .replace("mindmap.png", &format!("{}.png", &uuid))
-                .replace("const style = ``;", &format!("const style = `{}`;", INDEX))
-                .replace("const katex = ``;", &format!("const katex = `{}`;", KATEX))
-                .replace("MindElixir.new('root')", &format!("JSON.parse('{}')", &content));
-            if PARAS.language != "en" {
-                html = html.replace("locale: 'en'", &format!("locale: '{}'", PARAS.language));
-            }
-            if let Some(l) = label {
-                html = html.replace("placeholder='mindmap label'>", &format!("placeholder='{}'>", l));
-            }
-            html.into()
-        },
-        None => {
-            DEFAULT_PAGE
-                .replace("127.0.0.1:8081", &format!("{}:{}", &PARAS.addr_str, PARAS.port))
-                .replace("download/mindmap", &format!("download/{}", &uuid))
-                .replace("locale: 'en'", &format!("locale: '{}'", PARAS.language))
-                .replace("<option value='mindmap' selected>mindmap</option>", &format!("{}\n", pulldown))
-                .replace("mindmap.png", &format!("{}.png", &uuid))
-                .replace("const style = ``;", &format!("const style = `{}`;", INDEX))
-                .replace("const katex = ``;", &format!("const katex = `{}`;", KATEX))
-                .into()
-        },
+    let mut headers = HeaderMap::new();
+    if let Some(r) = revision {
+        headers.insert(header::ETAG, etag(r));
+    }
+    let node = node.filter(|n| !n.is_empty());
+    let mut status = StatusCode::OK;
+    if let Some(n) = &node {
+        let found = content_label.as_ref().is_some_and(|(content, _)| {
+            serde_json::from_str::<MindMapData>(content).is_ok_and(|d| d.node_data.find(n).is_some())
+        });
+        if !found {
+            event!(Level::INFO, "GET `{}`, node {:?} not found in {}", uri.path(), n, uuid);
+            status = StatusCode::NOT_FOUND;
+        }
     }
+    let html = match content_label {
+        Some((content, label)) => editor_page(&uuid, &pulldown, Some(&content), label.as_deref(), revision, node.as_deref()),
+        None => editor_page(&uuid, &pulldown, None, None, None, node.as_deref()),
+    };
+    (status, headers, html.into())
+}
+
+/// Handler for `/previous` GET
+/// `?node=<node_id>` selects, expands ancestors of and centers that node on load
+pub async fn previous(Query(params): Query<PreviousParams>, uri: OriginalUri) -> (StatusCode, HeaderMap, Html<String>) {
+    previous_page(params.uuid, params.node, &uri)
+}
+
+/// Handler for `/m/:uuid/:node_id` GET
+/// permalink of one node, same as `/previous?uuid=<uuid>&node=<node_id>`
+pub async fn permalink(Path((uuid, node_id)): Path<(MapId, String)>, uri: OriginalUri) -> (StatusCode, HeaderMap, Html<String>) {
+    previous_page(Some(uuid), Some(node_id), &uri)
 }
@@ -0,0 +1,32 @@
+use axum::{
+    body::Body,
+    extract::OriginalUri,
+    http::{header, HeaderMap},
+};
+use tracing::{event, Level};
+
+use crate::{
+    convert::{export_unlocked, ExportOptions, Format},
+    error::MyError,
+    etag::etag,
+    extract::Path,
+    map_id::MapId,
+};
+
+/// Handler for `/render/:uuid.svg`, `/render/:uuid.png` and `/render/:uuid.pdf` GET
+/// render mindmap as image in the server, laid out like the editor, shown inline instead of downloaded
+pub async fn render(Path(file): Path<String>, uri: OriginalUri) -> Result<(HeaderMap, Body), MyError> {
+    let (uuid, format) = match file.rsplit_once('.') {
+        Some((uuid, extension)) => (uuid.parse::<MapId>()?, extension.parse::<Format>()?),
+        None => return Err(MyError::ParaError{para: format!("{} has no image extension, e.g. `.svg`", file)}),
+    };
+    if !format.is_image() {
+        return Err(MyError::ParaError{para: format!("can not render {} format, support: svg, png, pdf", format)})
+    }
+    let (content, revision) = export_unlocked(&uuid, format, ExportOptions::default()).await?;
+    event!(Level::INFO, "GET `{}`, render {}.{}", uri.path(), uuid, format.extension());
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, format.content_type().parse().unwrap());
+    headers.insert(header::ETAG, etag(revision));
+    Ok((headers, Body::from(content)))
+}
@@ -0,0 +1,63 @@
+use axum::{
+    extract::OriginalUri,
+    response::Html,
+};
+use serde::Deserialize;
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    simple_page,
+    error::MyError,
+    escape::{encode_uri_component, escape_html},
+    extract::{Json, Query},
+    search::SearchHit,
+};
+
+/// `/search` and `/api/v1/search` query parameters
+#[derive(Deserialize)]
+pub struct SearchParams {
+    #[serde(default)]
+    q:     String,
+    limit: Option<usize>, // default 50
+}
+
+/// Handler for `/api/v1/search` GET
+/// return matched uuid, node id, node path and snippet
+pub async fn api_search(Query(params): Query<SearchParams>, uri: OriginalUri) -> Result<Json<Vec<SearchHit>>, MyError> {
+    let hits = DATA.write().unwrap().search(&params.q, params.limit.unwrap_or(50));
+    event!(Level::INFO, "GET `{}`, search {:?}, {} hits", uri.path(), params.q, hits.len());
+    Ok(Json(hits))
+}
+
+/// Handler for `/search` GET
+/// search box and result page, each hit links to the mindmap and node
+pub async fn search_page(Query(params): Query<SearchParams>, uri: OriginalUri) -> Result<Html<String>, MyError> {
+    let hits = DATA.write().unwrap().search(&params.q, params.limit.unwrap_or(50));
+    event!(Level::INFO, "GET `{}`, search {:?}, {} hits", uri.path(), params.q, hits.len());
+    let rows: Vec<String> = hits.iter().map(|hit| {
+        let mut href = format!("/previous?uuid={}", hit.uuid);
+        if let Some(node_id) = &hit.node_id {
+            href.push_str(&format!("&node={}", encode_uri_component(node_id)));
+        }
+        format!(
+            "      <tr><td><a href='{href}'>{snippet}</a></td><td>{path}</td><td>{label}</td></tr>",
+            href = escape_html(&href),
+            snippet = escape_html(&hit.snippet),
+            path = escape_html(&hit.node_path.join(" > ")),
+            label = escape_html(hit.label.as_deref().unwrap_or(hit.uuid.as_str())),
+        )
+    }).collect();
+    let mut body = format!(
+        "    <form method='get' action='/search'><input type='text' name='q' value='{q}' size='40' autofocus /> <button type='submit'>search</button></form>",
+        q = escape_html(&params.q),
+    );
+    if !params.q.trim().is_empty() {
+        body.push_str(&format!(
+            "\n    <h3>{num} results</h3>\n    <table>\n      <tr><th>match</th><th>node path</th><th>mindmap</th></tr>\n{rows}\n    </table>",
+            num = hits.len(),
+            rows = rows.join("\n"),
+        ));
+    }
+    Ok(simple_page("MindMap search", &body).into())
+}
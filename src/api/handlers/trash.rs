@@ -0,0 +1,46 @@
+use axum::{
+    extract::OriginalUri,
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use tracing::{event, Level};
+
+use crate::{
+    DATA,
+    error::MyError,
+    extract::Path,
+    map_id::MapId,
+    purge_trash,
+    trash_list,
+};
+
+/// mindmap in trash
+#[derive(Serialize)]
+pub struct TrashItem {
+    uuid:  MapId,
+    label: Option<String>,
+}
+
+/// Handler for `/trash` GET
+/// list all mindmap in trash
+pub async fn trash(uri: OriginalUri) -> Json<Vec<TrashItem>> {
+    event!(Level::INFO, "GET `{}`", uri.path());
+    Json(trash_list().into_iter().map(|(uuid, label)| TrashItem{uuid, label}).collect())
+}
+
+/// Handler for `/trash/:uuid/restore` POST
+/// move mindmap from trash back to outpath
+pub async fn trash_restore(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    DATA.write().unwrap().restore_trash(&uuid)?;
+    event!(Level::INFO, "POST `{}`, {} restored", uri.path(), uuid);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for `/trash/:uuid` DELETE
+/// permanently remove mindmap from trash
+pub async fn trash_purge(Path(uuid): Path<MapId>, uri: OriginalUri) -> Result<StatusCode, MyError> {
+    purge_trash(&uuid)?;
+    event!(Level::INFO, "DELETE `{}`, {} purged", uri.path(), uuid);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,35 +1,51 @@
-use std::collections::HashMap;
-
-use axum::extract::{Query, OriginalUri};
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use serde::Deserialize;
 use tracing::{event, Level};
 
-use crate::DATA;
+use crate::{
+    DATA,
+    error::MyError,
+    etag::{etag, if_match},
+    extract::Query,
+    map_id::MapId,
+    model::MindMapData,
+};
+
+/// `/update` query parameters, invalid or missing uuid is rejected with 400
+#[derive(Deserialize)]
+pub struct UpdateParams {
+    uuid:  MapId,
+    label: Option<String>,
+}
 
 /// Handler for `/update` POST
-pub async fn update(Query(params): Query<HashMap<String, String>>, uri: OriginalUri, body: String) -> () {
-    // get uuid
-    let uuid = match params.get("uuid") {
-        Some(u) => {
-            event!(Level::INFO, "{} POST `{}`", u, uri.path());
-            u.clone()
-        },
-        None => {
-            event!(Level::ERROR, "missing uuid POST `{}`", uri.path());
-            return
-        },
-    };
+/// body must be valid mind-elixir data, otherwise 422 with the reason
+/// if `If-Match` is given and not the current revision, 409 with the current revision
+/// return the new revision as `ETag`
+pub async fn update(Query(params): Query<UpdateParams>, uri: OriginalUri, headers: HeaderMap, body: String) -> Result<impl IntoResponse, MyError> {
+    let uuid = params.uuid;
+    event!(Level::INFO, "{} POST `{}`", uuid, uri.path());
+    // validate body, store the original string so fields unknown to the model are kept
+    if let Err(e) = MindMapData::parse(&body) {
+        event!(Level::ERROR, "{} POST `{}`, {}", uuid, uri.path(), e);
+        return Err(e)
+    }
     // get label
-    let label = match params.get("label") {
-        Some(l) => {
-            if l.is_empty() {
-                None
-            } else {
-                Some(l.clone())
-            }
-        },
-        None => None,
-    };
+    let label = params.label.filter(|l| !l.is_empty());
     // update loaded by body
     let mut data = DATA.write().unwrap();
-    data.update_loaded_mindmap(uuid, body, label);
+    // check revision, 0 means new mindmap
+    let current = data.revision(&uuid).unwrap_or(0);
+    if !if_match(&headers, current) {
+        event!(Level::WARN, "{} POST `{}`, revision conflict, current revision is {}", uuid, uri.path(), current);
+        return Err(MyError::RevisionConflictError{uuid: uuid.to_string(), revision: current})
+    }
+    let revision = data.update_loaded_mindmap(uuid.clone(), body, label);
+    // write-through, save to local immediately
+    data.write_through(&uuid)?;
+    Ok([(header::ETAG, etag(revision))])
 }
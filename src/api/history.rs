@@ -0,0 +1,107 @@
+use std::cmp::Reverse;
+use std::fs::{create_dir_all, read_to_string, remove_file};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, TimeZone};
+use tracing::{event, Level};
+
+use crate::{
+    error::MyError,
+    map_id::MapId,
+    parse_paras::PARAS,
+    storage::atomic_write,
+};
+
+/// history directory of one mindmap, e.g. `./mindmap/f66bedbd-9972-4ec3-9a30-9510d4fffe1c/history`
+pub fn history_dir(uuid: &MapId) -> PathBuf {
+    PARAS.outpath.join(uuid).join("history")
+}
+
+/// current unix timestamp in milliseconds, used as revision id
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// format revision id (unix timestamp in milliseconds) as local time, e.g. `2025-10-31 12:30:59`
+pub fn format_rev(rev: u64) -> String {
+    match Local.timestamp_millis_opt(rev as i64).single() {
+        Some(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => rev.to_string(),
+    }
+}
+
+/// format time as local time, e.g. `2025-10-31 12:30:59`
+pub fn format_time(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// all revisions of one mindmap, return (revision id, file size), newest first
+pub fn list_revisions(uuid: &MapId) -> Vec<(u64, u64)> {
+    let mut revs = Vec::new();
+    if let Ok(entries) = history_dir(uuid).read_dir() {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(rev) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u64>().ok()) {
+                    revs.push((rev, entry.metadata().map(|m| m.len()).unwrap_or(0)));
+                }
+            }
+        }
+    }
+    revs.sort_by_key(|r| Reverse(r.0));
+    revs
+}
+
+/// read one revision of mindmap data
+pub fn read_revision(uuid: &MapId, rev: u64) -> Result<String, MyError> {
+    let path = history_dir(uuid).join(format!("{}.json", rev));
+    if !path.is_file() {
+        return Err(MyError::FileNotExistError{file: path.display().to_string()})
+    }
+    Ok(read_to_string(&path)?)
+}
+
+/// save mindmap data as a new revision, skip if it is the same as the newest revision, then drop expired revisions
+pub fn save_revision(uuid: &MapId, content: &str) -> Result<(), MyError> {
+    let dir = history_dir(uuid);
+    if !dir.is_dir() {
+        if let Err(e) = create_dir_all(&dir) {
+            return Err(MyError::CreateDirAllError{dir_name: dir.display().to_string(), error: e})
+        }
+    }
+    let revs = list_revisions(uuid);
+    if let Some((newest, _)) = revs.first() {
+        if read_revision(uuid, *newest).is_ok_and(|c| c == content) {
+            return Ok(())
+        }
+    }
+    // revision id must be unique and increasing
+    let mut rev = now_millis();
+    if let Some((newest, _)) = revs.first() {
+        if rev <= *newest {
+            rev = newest + 1;
+        }
+    }
+    atomic_write(&dir.join(format!("{}.json", rev)), content)?;
+    prune(&dir, uuid);
+    Ok(())
+}
+
+/// drop revisions exceeding `history_count` or older than `history_days`, the newest one is always kept
+fn prune(dir: &Path, uuid: &MapId) {
+    let revs = list_revisions(uuid);
+    let oldest_allowed = if PARAS.history_days > 0 {
+        now_millis().saturating_sub(Duration::from_secs(PARAS.history_days * 24 * 3600).as_millis() as u64)
+    } else {
+        0
+    };
+    for (i, (rev, _)) in revs.iter().enumerate().skip(1) {
+        if (PARAS.history_count > 0 && i >= PARAS.history_count) || *rev < oldest_allowed {
+            let path = dir.join(format!("{}.json", rev));
+            if let Err(e) = remove_file(&path) {
+                event!(Level::ERROR, "{}: remove expired revision {}: {}", uuid, path.display(), e);
+            }
+        }
+    }
+}
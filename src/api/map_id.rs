@@ -0,0 +1,81 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::MyError;
+
+/// mindmap id, a lowercase hyphenated uuid, e.g. `f66bedbd-9972-4ec3-9a30-9510d4fffe1c`
+/// it is used as directory name, file name and html content, so anything else is rejected
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MapId(String);
+
+impl MapId {
+    /// create a new random mindmap id
+    pub fn new() -> Self {
+        MapId(Uuid::new_v4().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for MapId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromStr for MapId {
+    type Err = MyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // only accept the exact format generated by `Uuid::to_string`, no braces, urn prefix or uppercase
+        match Uuid::try_parse(s) {
+            Ok(u) if u.to_string() == s => Ok(MapId(s.to_string())),
+            _ => Err(MyError::InvalidMapIdError{id: s.chars().take(64).collect()}),
+        }
+    }
+}
+
+impl TryFrom<String> for MapId {
+    type Error = MyError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MapId> for String {
+    fn from(id: MapId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for MapId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for MapId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<std::path::Path> for MapId {
+    fn as_ref(&self) -> &std::path::Path {
+        self.0.as_ref()
+    }
+}
+
+impl Borrow<str> for MapId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
@@ -1,22 +1,49 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::fs::{write, read_to_string, create_dir_all};
-use std::path::PathBuf;
+use std::fs::{read_to_string, create_dir_all, remove_dir_all, remove_file, rename};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::sync::RwLock;
+use std::time::SystemTime;
 
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Deserialize;
 use tracing::{event, Level};
 
 pub mod parse_paras;
 pub mod router;
 pub mod error;
 pub mod ctrlc;
+pub mod autosave;
+pub mod storage;
+pub mod history;
+pub mod map_id;
+pub mod escape;
+pub mod model;
+pub mod extract;
+pub mod etag;
+pub mod search;
+pub mod convert;
 
 mod handlers;
 
-use crate::parse_paras::PARAS;
+use crate::{
+    error::MyError,
+    escape::{escape_html, escape_js_string},
+    history::{read_revision, save_revision},
+    map_id::MapId,
+    model::MindMapData,
+    parse_paras::PARAS,
+    search::{SearchHit, SearchIndex},
+    storage::{atomic_write, atomic_write_with_backup, bak_path, read_mindmap_json},
+};
 
 /// mindmap default page
 pub const DEFAULT_PAGE: &str = include_str!("../../assets/mindmap.html");
+/// simple page for history list, title and body must be escaped by caller
+pub const SIMPLE_PAGE: &str = include_str!("../../assets/simple.html");
+/// read-only page for sharing, mind-elixir script, style and icon are taken from the default page
+pub const VIEWER_PAGE: &str = include_str!("../../assets/viewer.html");
 /// style for export png
 pub const INDEX: &str = include_str!("../../assets/index.css");
 pub const KATEX: &str = include_str!("../../assets/katex.css");
@@ -24,10 +51,167 @@ pub const KATEX: &str = include_str!("../../assets/katex.css");
 /// global data, store all mindmap
 pub static DATA: Lazy<RwLock<MindMap>> = Lazy::new(|| RwLock::new(MindMap::new()));
 
+/// prepare mindmap editor page, `content` and `revision` are None for new mindmap, `node` is the node to focus on load
+/// user data (node id, mindmap data, pulldown, label) is escaped and inserted last, so it can not match the other placeholders
+pub fn editor_page(uuid: &MapId, pulldown: &str, content: Option<&str>, label: Option<&str>, revision: Option<u64>, node: Option<&str>) -> String {
+    let mut html = DEFAULT_PAGE
+        .replace("127.0.0.1:8081", &format!("{}:{}", &PARAS.addr_str, PARAS.port))
+        .replace("download/mindmap", &format!("download/{}", uuid))
+        .replace("locale: 'en'", &format!("locale: '{}'", PARAS.language))
+        .replace("mindmap.png", &format!("{}.png", uuid))
+        .replace("const style = ``;", &format!("const style = `{}`;", INDEX))
+        .replace("const katex = ``;", &format!("const katex = `{}`;", KATEX));
+    if let Some(r) = revision {
+        html = html.replace("let revision = '';", &format!("let revision = '{}';", r));
+    }
+    if let Some(n) = node {
+        html = html.replace("let focus_node = '';", &format!("let focus_node = '{}';", escape_js_string(n)));
+    }
+    if let Some(c) = content {
+        html = html.replace("MindElixir.new('root')", &format!("JSON.parse('{}')", escape_js_string(c)));
+    }
+    html = html.replace("<option value='mindmap' selected>mindmap</option>", &format!("{}\n", pulldown));
+    if let Some(l) = label {
+        html = html.replace("placeholder='mindmap label'>", &format!("placeholder='{}'>", escape_html(l)));
+    }
+    html
+}
+
+/// lines of `page` after the line of `start`, up to `end`, empty if not found
+fn page_section<'a>(page: &'a str, start: &str, end: &str) -> &'a str {
+    let from = match page.find(start).and_then(|i| page[i..].find('\n').map(|j| i + j + 1)) {
+        Some(f) => f,
+        None => return "",
+    };
+    match page[from..].find(end) {
+        Some(len) => page[from..from + len].trim(),
+        None => "",
+    }
+}
+
+/// prepare read-only page of one mindmap, without the controls of the server and with only the style mind-elixir needs
+/// it does not request the server, so the downloaded file can be opened anywhere
+pub fn viewer_page(content: &str, title: &str) -> String {
+    VIEWER_PAGE
+        .replace("locale: 'en'", &format!("locale: '{}'", PARAS.language))
+        .replace("<!-- icon -->", page_section(DEFAULT_PAGE, "<title>MindMap</title>", "<style>"))
+        .replace("/* MindElixir.css */", page_section(DEFAULT_PAGE, "<!-- https://cdn.jsdelivr.net/npm/mind-elixir", "</style>"))
+        .replace("// MindElixir.js", page_section(DEFAULT_PAGE, "// https://cdn.jsdelivr.net/npm/mind-elixir", "</script>"))
+        .replace("<title>MindMap</title>", &format!("<title>{}</title>", escape_html(title)))
+        .replace("MindElixir.new('root')", &format!("JSON.parse('{}')", escape_js_string(content)))
+}
+
+/// pulldown option of one mindmap, e.g. `<option value='uuid' selected>uuid(label)</option>`
+fn pulldown_option(uuid: &MapId, label: Option<&str>, selected: bool) -> String {
+    let uuid = escape_html(uuid.as_str());
+    let selected = if selected { " selected" } else { "" };
+    match label {
+        Some(l) => format!("<option value='{}'{}>{}({})</option>", uuid, selected, uuid, escape_html(l)),
+        None => format!("<option value='{}'{}>{}</option>", uuid, selected, uuid),
+    }
+}
+
+/// prepare simple page, e.g. history list
+pub fn simple_page(title: &str, body: &str) -> String {
+    SIMPLE_PAGE
+        .replace("<title>MindMap</title>", &format!("<title>{}</title>", title))
+        .replace("<!-- body -->", body)
+}
+
+/// trash dir, deleted mindmap is moved here, e.g. `./mindmap/.trash`
+pub fn trash_dir() -> PathBuf {
+    PARAS.outpath.join(".trash")
+}
+
+/// all mindmap in trash, return (uuid, label)
+pub fn trash_list() -> Vec<(MapId, Option<String>)> {
+    let mut list = Vec::new();
+    if let Ok(uuid_dirs) = trash_dir().read_dir() {
+        for entry in uuid_dirs.flatten() {
+            let uuid_path = entry.path();
+            if let Some(Ok(uuid)) = uuid_path.file_name().and_then(|n| n.to_str()).map(|n| n.parse::<MapId>()) {
+                if uuid_path.is_dir() {
+                    list.push((uuid.clone(), read_to_string(uuid_path.join(format!("{}.txt", uuid))).ok()));
+                }
+            }
+        }
+    }
+    list.sort();
+    list
+}
+
+/// permanently remove mindmap from trash
+pub fn purge_trash(uuid: &MapId) -> Result<(), MyError> {
+    let trash_path = trash_dir().join(uuid);
+    if !trash_path.is_dir() {
+        return Err(MyError::NotFoundError{what: format!("mindmap {} in trash", uuid)})
+    }
+    remove_dir_all(&trash_path)?;
+    event!(Level::INFO, "{} purged from trash", uuid);
+    Ok(())
+}
+
+/// read revision of local mindmap from `<uuid>.rev`, 1 if not exist
+fn read_revision_number(uuid: &MapId) -> u64 {
+    read_to_string(PARAS.outpath.join(uuid).join(format!("{}.rev", uuid)))
+        .ok()
+        .and_then(|r| r.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// modified time of local mindmap json file, None if not exist
+fn modified_time(json_path: &Path) -> Option<SystemTime> {
+    json_path.metadata().and_then(|m| m.modified()).ok()
+}
+
+/// created time of local mindmap directory, fall back to the json file modified time if not supported
+fn created_time(uuid: &MapId, json_path: &Path) -> Option<SystemTime> {
+    PARAS.outpath.join(uuid).metadata().and_then(|m| m.created()).ok().or_else(|| modified_time(json_path))
+}
+
+/// root topic, number of nodes and bytes of mindmap data, used for listing
+type MapStats = (String, usize, usize);
+
+/// local mindmap entry, (mindmap data file path, label string, archived, stats, revision, modified time)
+type LocalMap = (PathBuf, Option<String>, bool, OnceCell<MapStats>, u64, Option<SystemTime>);
+
+/// stats of mindmap data, ("", 0, size) if the data can not be parsed
+fn map_stats(content: &str) -> MapStats {
+    match serde_json::from_str::<MindMapData>(content) {
+        Ok(d) => (d.node_data.topic.clone(), d.node_data.descendants().len(), content.len()),
+        Err(_) => (String::new(), 0, content.len()),
+    }
+}
+
+/// summary of one mindmap, used for listing
+pub struct MapInfo {
+    pub uuid:       MapId,
+    pub label:      Option<String>,
+    pub root_topic: String,
+    pub created:    Option<SystemTime>, // mindmap not saved yet uses the modified time
+    pub modified:   Option<SystemTime>, // None if never saved
+    pub size:       usize, // bytes of mindmap data
+    pub node_count: usize,
+    pub revision:   u64,
+    pub archived:   bool,
+}
+
+/// sort order of mindmap listing
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MapSort {
+    #[default]
+    Modified, // newest first
+    Created, // newest first
+    Label, // alphabetical, mindmap without label last
+    Topic, // alphabetical by root topic
+}
+
 /// store local mindmap
 pub struct MindMap {
-    loaded: HashMap<String, (String, Option<String>, bool)>, // loaded mindmap, key: uuid, value: (mindmap data, label string, updated)
-    local:  HashMap<String, (PathBuf, Option<String>)>, // all mindmap in outpath, key : uuid, value: (mindmap data file path, label string), e.g. (f66bedbd-9972-4ec3-9a30-9510d4fffe1c.html, f66bedbd-9972-4ec3-9a30-9510d4fffe1c.json, "my first mindmap")
+    loaded: HashMap<MapId, (String, Option<String>, bool, u64, SystemTime)>, // loaded mindmap, key: uuid, value: (mindmap data, label string, updated, revision, modified time)
+    search: SearchIndex, // full-text index of loaded and local mindmap, built on the first search
+    local:  HashMap<MapId, LocalMap>, // all mindmap in outpath, key : uuid, value: (mindmap data file path, label string, archived, (root topic, node count, size) of the saved data, saved revision, saved modified time), e.g. (f66bedbd-9972-4ec3-9a30-9510d4fffe1c.json, "my first mindmap", false, ("root", 12, 1024), 3, 2024-01-01 00:00:00)
 }
 
 impl MindMap {
@@ -39,12 +223,15 @@ impl MindMap {
                 if let Ok(entry) = i {
                     let uuid_path = entry.path();
                     if uuid_path.is_dir() {
-                        if let Some(uuid) = uuid_path.file_name().unwrap().to_str() {
+                        // skip `.trash` and any directory not named by a valid uuid
+                        if let Some(Ok(uuid)) = uuid_path.file_name().unwrap().to_str().map(|n| n.parse::<MapId>()) {
                             let json_path = uuid_path.join(&format!("{}.json", uuid));
                             let label_path = uuid_path.join(&format!("{}.txt", uuid));
-                            if json_path.exists() && json_path.is_file() {
+                            let archived = uuid_path.join(format!("{}.archived", uuid)).is_file();
+                            if json_path.is_file() || bak_path(&json_path).is_file() {
+                                let modified = modified_time(&json_path);
                                 local.insert(
-                                    uuid.to_string(),
+                                    uuid.clone(),
                                     (
                                         json_path,
                                         if label_path.exists() && label_path.is_file() {
@@ -58,6 +245,10 @@ impl MindMap {
                                         } else {
                                             None
                                         },
+                                        archived,
+                                        OnceCell::new(), // filled on the first listing
+                                        read_revision_number(&uuid),
+                                        modified,
                                     ),
                                 );
                             }
@@ -68,173 +259,399 @@ impl MindMap {
         }
         Self {
             loaded: HashMap::new(), // not load any mindmap
+            search: SearchIndex::default(),
             local,
         }
     }
 
-    /// get all mindmap pulldown option string vec
-    fn pulldown(&self, uuid: &str) -> Vec<String> {
-        let mut pulldown: HashMap<String, String> = HashMap::new(); // key: uuid, value: pulldown option string
-        let mut selected: &str;
-        let mut uuid_inserted = false;
-        // loaded, some new created mindmap in loaded but not in local, so first insert loaded
+    /// get all mindmap pulldown option string vec, most recently modified first
+    fn pulldown(&self, uuid: &MapId) -> Vec<String> {
+        let mut pulldown = Vec::new();
+        // new mindmap not in loaded or local, insert it first
+        if !self.contains(uuid) {
+            pulldown.push(pulldown_option(uuid, None, true));
+        }
+        // only in-memory data here, it runs on every page load
+        let mut maps: Vec<(&MapId, Option<&str>, Option<SystemTime>)> = Vec::new();
         for (k, v) in &self.loaded {
-            selected = if k == uuid {
-                uuid_inserted = true;
-                " selected"
-            } else {
-                ""
-            };
-            pulldown.insert(
-                k.clone(),
-                match &v.1 {
-                    Some(label) => format!("<option value='{}'{}>{}({})</option>", k, selected, k, label),
-                    None => format!("<option value='{}'{}>{}</option>", k, selected, k),
-                }
-            );
+            maps.push((k, v.1.as_deref(), Some(v.4)));
         }
-        // local, insert mindmap not in loaded but in local
         for (k, v) in &self.local {
-            if !pulldown.contains_key(k) {
-                selected = if k == uuid {
-                    uuid_inserted = true;
-                    " selected"
-                } else {
-                    ""
-                };
-                pulldown.insert(
-                    k.clone(),
-                    match &v.1 {
-                        Some(label) => format!("<option value='{}'{}>{}({})</option>", k, selected, k, label),
-                        None => format!("<option value='{}'{}>{}</option>", k, selected, k),
-                    }
-                );
+            if !self.loaded.contains_key(k) {
+                maps.push((k, v.1.as_deref(), v.5));
             }
         }
-        // if uuid not inserted, insert it
-        if !uuid_inserted {
-            pulldown.insert(
-                uuid.to_string(),
-                format!("<option value='{}' selected>{}</option>", uuid, uuid),
-            );
+        maps.retain(|(k, _, _)| *k == uuid || !self.is_archived(k)); // hide archived mindmap
+        maps.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.as_str().cmp(b.0.as_str())));
+        for (k, label, _) in maps {
+            pulldown.push(pulldown_option(k, label, k == uuid));
         }
-        pulldown.into_iter().map(|(_, v)| v).collect()
+        pulldown
     }
 
     /// get all mindmap pulldown option string
-    pub fn html_pulldown(&self, uuid: &str) -> String {
+    pub fn html_pulldown(&self, uuid: &MapId) -> String {
         self.pulldown(uuid).join("\n          ")
     }
 
     /// get local mindmap by uuid, return ((mindmap data, label), pulldown)
-    pub fn get_local_mindmap(&mut self, uuid: &str) -> (Option<(String, Option<String>)>, String) {
-        let mut add_to_loaded = false;
+    pub fn get_local_mindmap(&mut self, uuid: &MapId) -> (Option<(String, Option<String>)>, String) {
         let pulldown = self.pulldown(uuid);
+        (self.load_mindmap(uuid), pulldown.join("\n          "))
+    }
+
+    /// get mindmap by uuid, read it from local and add to loaded if not loaded, return (mindmap data, label)
+    pub fn load_mindmap(&mut self, uuid: &MapId) -> Option<(String, Option<String>)> {
+        let mut add_to_loaded = false;
         let result = match (self.local.get(uuid), self.loaded.get(uuid)) {
-            (Some(_), Some((content, label, _))) => Some((content.clone(), label.clone())), // already loaded mindmap
-            (Some((json_file, label)), None) => { // in local, but not loaded
-                match read_to_string(&json_file) {
+            (Some(_), Some((content, label, _, _, _))) => Some((content.clone(), label.clone())), // already loaded mindmap
+            (Some((json_file, label, ..)), None) => { // in local, but not loaded
+                match read_mindmap_json(json_file) {
                     Ok(content) => {
                         add_to_loaded = true;
                         Some((content, label.clone()))
                     },
                     Err(e) => {
-                        event!(Level::ERROR, "read {} to string: {}", json_file.display(), e);
+                        event!(Level::ERROR, "read {}: {}", json_file.display(), e);
                         None
                     },
                 }
             },
-            (None, Some((content, label, _))) => Some((content.clone(), label.clone())), // created new mindmap, not in local
+            (None, Some((content, label, _, _, _))) => Some((content.clone(), label.clone())), // created new mindmap, not in local
             (None, None) => None,
         };
         if add_to_loaded {
             if let Some((content, label)) = &result {
-                self.loaded.insert(uuid.to_string(), (content.clone(), label.clone(), false));
+                let (revision, modified) = match self.local.get(uuid) {
+                    Some(v) => {
+                        v.3.get_or_init(|| map_stats(content));
+                        (v.4, v.5.unwrap_or_else(SystemTime::now))
+                    },
+                    None => (1, SystemTime::now()),
+                };
+                self.loaded.insert(uuid.clone(), (content.clone(), label.clone(), false, revision, modified));
             }
         }
-        (result, pulldown.join("\n          "))
+        result
     }
 
-    /// update loaded mindmap
-    pub fn update_loaded_mindmap(&mut self, uuid: String, content: String, label: Option<String>) {
-        match self.loaded.get_mut(&uuid) {
+    /// check uuid in loaded or local
+    pub fn contains(&self, uuid: &MapId) -> bool {
+        self.loaded.contains_key(uuid) || self.local.contains_key(uuid)
+    }
+
+    /// check mindmap is archived, archived mindmap is hidden from the pulldown
+    pub fn is_archived(&self, uuid: &MapId) -> bool {
+        self.local.get(uuid).is_some_and(|v| v.2)
+    }
+
+    /// archive or unarchive mindmap, unsaved mindmap will be saved first
+    pub fn set_archived(&mut self, uuid: &MapId, archived: bool) -> Result<(), MyError> {
+        if !self.contains(uuid) {
+            return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
+        }
+        if !self.local.contains_key(uuid) {
+            self.save_loaded_mindmap(uuid)?;
+        }
+        let marker = PARAS.outpath.join(uuid).join(format!("{}.archived", uuid));
+        if archived {
+            atomic_write(&marker, "")?;
+        } else if marker.is_file() {
+            remove_file(&marker)?;
+        }
+        if let Some(v) = self.local.get_mut(uuid) {
+            v.2 = archived;
+        }
+        event!(Level::INFO, "{} {}", uuid, if archived { "archived" } else { "unarchived" });
+        Ok(())
+    }
+
+    /// move mindmap dir to `outpath/.trash`, and drop it from loaded and local
+    pub fn delete_mindmap(&mut self, uuid: &MapId) -> Result<(), MyError> {
+        if !self.contains(uuid) {
+            return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)})
+        }
+        // save unsaved changes first, so the trash keeps the latest content
+        if self.loaded.get(uuid).is_some_and(|v| v.2) {
+            self.save_loaded_mindmap(uuid)?;
+        }
+        let uuid_path = PARAS.outpath.join(uuid);
+        if uuid_path.is_dir() {
+            let trash_dir = trash_dir();
+            if let Err(e) = create_dir_all(&trash_dir) {
+                return Err(MyError::CreateDirAllError{dir_name: trash_dir.display().to_string(), error: e})
+            }
+            let trash_path = trash_dir.join(uuid);
+            if trash_path.exists() { // replace older trashed copy
+                remove_dir_all(&trash_path)?;
+            }
+            rename(&uuid_path, &trash_path)?;
+        }
+        self.loaded.remove(uuid);
+        self.local.remove(uuid);
+        self.search.remove_map(uuid);
+        event!(Level::INFO, "{} moved to trash", uuid);
+        Ok(())
+    }
+
+    /// move mindmap dir from `outpath/.trash` back to outpath
+    pub fn restore_trash(&mut self, uuid: &MapId) -> Result<(), MyError> {
+        let trash_path = trash_dir().join(uuid);
+        if !trash_path.is_dir() {
+            return Err(MyError::NotFoundError{what: format!("mindmap {} in trash", uuid)})
+        }
+        let uuid_path = PARAS.outpath.join(uuid);
+        if uuid_path.exists() || self.contains(uuid) {
+            return Err(MyError::ConflictError{reason: format!("{} already exists, can not restore from trash", uuid)})
+        }
+        rename(&trash_path, &uuid_path)?;
+        let label = read_to_string(uuid_path.join(format!("{}.txt", uuid))).ok();
+        let archived = uuid_path.join(format!("{}.archived", uuid)).is_file();
+        let json_path = uuid_path.join(format!("{}.json", uuid));
+        if self.search.is_built() {
+            if let Ok(content) = read_mindmap_json(&json_path) {
+                self.search.index_map(uuid, &content, label.as_deref());
+            }
+        }
+        let modified = modified_time(&json_path);
+        self.local.insert(uuid.clone(), (json_path, label, archived, OnceCell::new(), read_revision_number(uuid), modified));
+        event!(Level::INFO, "{} restored from trash", uuid);
+        Ok(())
+    }
+
+    /// get mindmap label by uuid
+    pub fn label(&self, uuid: &MapId) -> Option<String> {
+        match (self.loaded.get(uuid), self.local.get(uuid)) {
+            (Some((_, label, _, _, _)), _) => label.clone(),
+            (None, Some((_, label, ..))) => label.clone(),
+            (None, None) => None,
+        }
+    }
+
+    /// current revision of mindmap, it increases on every update, load it from local if not loaded
+    pub fn revision(&mut self, uuid: &MapId) -> Option<u64> {
+        self.load_mindmap(uuid)?;
+        self.loaded.get(uuid).map(|v| v.3)
+    }
+
+    /// update loaded mindmap, return the new revision
+    pub fn update_loaded_mindmap(&mut self, uuid: MapId, content: String, label: Option<String>) -> u64 {
+        self.load_mindmap(&uuid); // continue the revision of local mindmap
+        let revision = match self.loaded.get_mut(&uuid) {
             Some(data) => {
-                event!(Level::INFO, "{} update mindmap in server", &uuid);
-                *data = (content, label, true);
+                let revision = data.3 + 1;
+                event!(Level::INFO, "{} update mindmap in server, revision {}", &uuid, revision);
+                *data = (content, label, true, revision, SystemTime::now());
+                revision
             },
             None => {
                 event!(Level::INFO, "create {} mindmap in server", uuid);
-                self.loaded.insert(uuid, (content, label, true));
+                self.loaded.insert(uuid.clone(), (content, label, true, 1, SystemTime::now()));
+                1
             },
+        };
+        if self.search.is_built() {
+            let (content, label, ..) = &self.loaded[&uuid];
+            self.search.index_map(&uuid, content, label.as_deref());
         }
+        revision
     }
 
-    /// prepare uuid mindmap html content for download
-    pub fn html_content(&self, uuid: &str) -> Option<String> {
-        if let Some(value) = self.loaded.get(uuid) {
-            let mut html = DEFAULT_PAGE
-                .replace("<option value='mindmap' selected>mindmap</option>", &format!("<option value='{}' selected>{}</option>", uuid, uuid))
-                .replace("mindmap.png", &format!("{}.png", uuid))
-                .replace("const style = ``;", &format!("const style = `{}`;", INDEX))
-                .replace("const katex = ``;", &format!("const katex = `{}`;", KATEX))
-                .replace("MindElixir.new('root')", &format!("JSON.parse('{}')", &value.0));
-            if PARAS.language != "en" {
-                html = html.replace("locale: 'en'", &format!("locale: '{}'", PARAS.language));
+    /// save mindmap to local immediately if `-w` is set
+    pub fn write_through(&mut self, uuid: &MapId) -> Result<(), MyError> {
+        if PARAS.write_through {
+            if let Err(e) = self.save_loaded_mindmap(uuid) {
+                return Err(MyError::StorageError{uuid: uuid.to_string(), reason: e.to_string()})
             }
-            if let Some(l) = &value.1 {
-                html = html.replace("placeholder='mindmap label'>", &format!("placeholder='{}'>", l));
-            }
-            Some(html)
-        } else {
-            None
         }
+        Ok(())
     }
 
-    /// save all updated mindmap to local
-    pub fn save_mindmap(&self) {
+    /// all mindmap in loaded and local, without loading them
+    /// `filter` keeps mindmap whose uuid, label or root topic contains it, case insensitive
+    pub fn list_mindmaps(&self, sort: MapSort, filter: Option<&str>) -> Vec<MapInfo> {
+        let mut list = Vec::new();
         for (k, v) in &self.loaded {
-            if v.2 {
-                // check path exist
-                let save_path = PARAS.outpath.join(k);
-                if !(save_path.exists() && save_path.is_dir()) {
-                    if let Err(e) = create_dir_all(&save_path) {
-                        event!(Level::ERROR, "create dir {} error: {}", save_path.display(), e);
-                        continue
+            // loaded mindmap not updated since load or save is the same as local
+            let (root_topic, node_count, size) = match self.local.get(k) {
+                Some(l) if !v.2 => l.3.get_or_init(|| map_stats(&v.0)).clone(),
+                _ => map_stats(&v.0),
+            };
+            list.push(MapInfo{
+                uuid:       k.clone(),
+                label:      v.1.clone(),
+                root_topic,
+                created:    self.local.get(k).and_then(|l| created_time(k, &l.0)).or(Some(v.4)),
+                modified:   Some(v.4),
+                size,
+                node_count,
+                revision:   v.3,
+                archived:   self.is_archived(k),
+            });
+        }
+        for (k, v) in &self.local {
+            if !self.loaded.contains_key(k) {
+                // read only on the first listing if neither loaded nor saved before
+                let (root_topic, node_count, size) = v.3.get_or_init(|| map_stats(&read_mindmap_json(&v.0).unwrap_or_default())).clone();
+                list.push(MapInfo{
+                    uuid:       k.clone(),
+                    label:      v.1.clone(),
+                    root_topic,
+                    created:    created_time(k, &v.0),
+                    modified:   v.5,
+                    size,
+                    node_count,
+                    revision:   v.4,
+                    archived:   v.2,
+                });
+            }
+        }
+        if let Some(f) = filter.map(|f| f.trim().to_lowercase()).filter(|f| !f.is_empty()) {
+            list.retain(|m| {
+                m.uuid.as_str().contains(&f)
+                    || m.label.as_ref().is_some_and(|l| l.to_lowercase().contains(&f))
+                    || m.root_topic.to_lowercase().contains(&f)
+            });
+        }
+        // sort by uuid first, so mindmap with the same key keep a stable order
+        list.sort_by(|a, b| a.uuid.as_str().cmp(b.uuid.as_str()));
+        match sort {
+            MapSort::Modified => list.sort_by_key(|m| Reverse(m.modified)),
+            MapSort::Created => list.sort_by_key(|m| Reverse(m.created)),
+            MapSort::Label => list.sort_by_key(|m| (m.label.is_none(), m.label.as_ref().map(|l| l.to_lowercase()))),
+            MapSort::Topic => list.sort_by_key(|m| m.root_topic.to_lowercase()),
+        }
+        list
+    }
+
+    /// full-text search over node topics, tags and labels, index all mindmap on the first search
+    pub fn search(&mut self, query: &str, limit: usize) -> Vec<SearchHit> {
+        if !self.search.is_built() {
+            let mut num = 0;
+            for (k, v) in &self.loaded {
+                self.search.index_map(k, &v.0, v.1.as_deref());
+                num += 1;
+            }
+            for (k, v) in &self.local {
+                if !self.loaded.contains_key(k) {
+                    match read_mindmap_json(&v.0) {
+                        Ok(content) => {
+                            self.search.index_map(k, &content, v.1.as_deref());
+                            num += 1;
+                        },
+                        Err(e) => event!(Level::ERROR, "{}: index mindmap {}", k, e),
                     }
                 }
-                // save html
-                let file_name = format!("{}.html", k);
-                let file_path = save_path.join(file_name);
-                let mut html = DEFAULT_PAGE
-                    .replace("<option value='mindmap' selected>mindmap</option>", &format!("<option value='{}' selected>{}</option>", k, k))
-                    .replace("mindmap.png", &format!("{}.png", k))
-                    .replace("const style = ``;", &format!("const style = `{}`;", INDEX))
-                    .replace("const katex = ``;", &format!("const katex = `{}`;", KATEX))
-                    .replace("MindElixir.new('root')", &format!("JSON.parse('{}')", &v.0));
-                if PARAS.language != "en" {
-                    html = html.replace("locale: 'en'", &format!("locale: '{}'", PARAS.language));
-                }
-                if let Some(l) = &v.1 {
-                    html = html.replace("placeholder='mindmap label'>", &format!("placeholder='{}'>", l));
-                }
-                if let Err(e) = write(&file_path, html) {
-                    event!(Level::ERROR, "{}: save mindmap html {} error: {}", k, file_path.display(), e);
-                }
-                // save mindmap data
-                let file_name = format!("{}.json", k);
-                let file_path = save_path.join(file_name);
-                if let Err(e) = write(&file_path, &v.0) {
-                    event!(Level::ERROR, "{}: save mindmap data {} error: {}", k, file_path.display(), e);
-                }
-                // save label
-                if let Some(l) = &v.1 {
-                    let file_name = format!("{}.txt", k);
-                    let file_path = save_path.join(file_name);
-                    if let Err(e) = write(&file_path, l) {
-                        event!(Level::ERROR, "{}: save mindmap label {} error: {}", k, file_path.display(), e);
-                    }
+            }
+            self.search.set_built();
+            event!(Level::INFO, "search index built, {} mindmap", num);
+        }
+        self.search.search(query, limit)
+    }
+
+    /// make an old revision current, then save it to local
+    pub fn restore_revision(&mut self, uuid: &MapId, rev: u64) -> Result<(), MyError> {
+        let content = read_revision(uuid, rev)?;
+        let label = self.label(uuid);
+        event!(Level::INFO, "{} restore revision {}", uuid, rev);
+        self.update_loaded_mindmap(uuid.clone(), content, label);
+        self.save_loaded_mindmap(uuid)
+    }
+
+    /// prepare uuid mindmap html content for download, load it from local if not loaded, return (html, revision)
+    pub fn html_content(&mut self, uuid: &MapId) -> Option<(String, u64)> {
+        let (content, label) = self.load_mindmap(uuid)?;
+        let revision = self.revision(uuid)?;
+        Some((editor_page(uuid, &pulldown_option(uuid, label.as_deref(), true), Some(&content), label.as_deref(), Some(revision), None), revision))
+    }
+
+    /// save one loaded mindmap to local, clear the updated flag after success
+    pub fn save_loaded_mindmap(&mut self, uuid: &MapId) -> Result<(), MyError> {
+        let (content, label, updated, revision, modified) = match self.loaded.get_mut(uuid) {
+            Some(v) => v,
+            None => return Err(MyError::NotFoundError{what: format!("mindmap {}", uuid)}),
+        };
+        // check path exist
+        let save_path = PARAS.outpath.join(uuid);
+        if !(save_path.exists() && save_path.is_dir()) {
+            if let Err(e) = create_dir_all(&save_path) {
+                return Err(MyError::CreateDirAllError{dir_name: save_path.display().to_string(), error: e})
+            }
+        }
+        // save html
+        let file_path = save_path.join(format!("{}.html", uuid));
+        let html = editor_page(uuid, &pulldown_option(uuid, label.as_deref(), true), Some(content), label.as_deref(), Some(*revision), None);
+        atomic_write(&file_path, html)?;
+        // save mindmap data, keep the previous good copy as `<uuid>.json.bak`
+        let json_path = save_path.join(format!("{}.json", uuid));
+        atomic_write_with_backup(&json_path, &content)?;
+        // keep this revision in history
+        if let Err(e) = save_revision(uuid, content) {
+            event!(Level::ERROR, "{}: save mindmap revision {}", uuid, e);
+        }
+        // save label, a removed label also removes its file
+        let label_path = save_path.join(format!("{}.txt", uuid));
+        match label {
+            Some(l) => atomic_write(&label_path, l)?,
+            None => if let Err(e) = remove_file(&label_path) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(MyError::IoError(e))
                 }
+            },
+        }
+        // save revision
+        atomic_write(&save_path.join(format!("{}.rev", uuid)), revision.to_string())?;
+        *updated = false;
+        let label = label.clone();
+        let stats = OnceCell::with_value(map_stats(content));
+        let revision = *revision;
+        let modified = modified_time(&json_path).or(Some(*modified));
+        let archived = self.is_archived(uuid);
+        self.local.insert(uuid.clone(), (json_path, label, archived, stats, revision, modified));
+        Ok(())
+    }
+
+    /// save all updated mindmap to local, return (saved number, failed number)
+    pub fn save_mindmap(&mut self) -> (usize, usize) {
+        let updated: Vec<MapId> = self.loaded.iter().filter(|(_, v)| v.2).map(|(k, _)| k.clone()).collect();
+        let mut saved = 0;
+        let mut failed = 0;
+        for k in updated {
+            match self.save_loaded_mindmap(&k) {
+                Ok(_) => {
+                    event!(Level::INFO, "{}: save mindmap to {}", k, PARAS.outpath.join(&k).display());
+                    saved += 1;
+                },
+                Err(e) => {
+                    event!(Level::ERROR, "{}: save mindmap {}", k, e);
+                    failed += 1;
+                },
             }
         }
+        (saved, failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_label_stays_removed_after_reload() {
+        let uuid = MapId::new();
+        let content = r#"{"nodeData":{"id":"root","topic":"root"}}"#.to_string();
+        let mut data = MindMap::new();
+        data.update_loaded_mindmap(uuid.clone(), content.clone(), Some("hello".to_string()));
+        data.save_loaded_mindmap(&uuid).unwrap();
+        assert_eq!(MindMap::new().label(&uuid).as_deref(), Some("hello"));
+        data.update_loaded_mindmap(uuid.clone(), content, None);
+        data.save_loaded_mindmap(&uuid).unwrap();
+        let mut reloaded = MindMap::new();
+        assert_eq!(reloaded.label(&uuid), None);
+        assert_eq!(reloaded.load_mindmap(&uuid).map(|(_, label)| label), Some(None));
+        // saving again without label file is fine
+        data.update_loaded_mindmap(uuid.clone(), r#"{"nodeData":{"id":"root","topic":"new"}}"#.to_string(), None);
+        data.save_loaded_mindmap(&uuid).unwrap();
+        remove_dir_all(PARAS.outpath.join(&uuid)).unwrap();
     }
 }
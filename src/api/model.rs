@@ -0,0 +1,410 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::error::MyError;
+
+/// mind-elixir document, the result of `mind.getDataString()`
+/// https://github.com/SSShooter/mind-elixir-core/blob/master/src/types/index.ts
+/// unknown fields are kept in `extra`, so parse and serialize again does not lose data
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MindMapData {
+    pub node_data: NodeObj,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arrows:    Vec<Arrow>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summaries: Vec<Summary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<u8>, // 0: left, 1: right, 2: side
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme:     Option<Theme>,
+    #[serde(flatten)]
+    pub extra:     Map<String, Value>,
+}
+
+/// one node of the mindmap tree
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeObj {
+    pub id:          String,
+    pub topic:       String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style:       Option<NodeStyle>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children:    Vec<NodeObj>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags:        Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub icons:       Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hyper_link:  Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image:       Option<NodeImage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expanded:    Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction:   Option<u8>, // only for main branch, 0: left, 1: right
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note:        Option<String>,
+    #[serde(flatten)]
+    pub extra:       Map<String, Value>,
+}
+
+/// node style, css properties of the topic
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size:   Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color:       Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background:  Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_weight: Option<String>,
+    #[serde(flatten)]
+    pub extra:       Map<String, Value>,
+}
+
+/// node tag, a plain string or an object with class name and style
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Tag {
+    Text(String),
+    Object {
+        text: String,
+        #[serde(default, rename = "className", skip_serializing_if = "Option::is_none")]
+        class_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<Map<String, Value>>,
+    },
+}
+
+impl Tag {
+    pub fn text(&self) -> &str {
+        match self {
+            Tag::Text(t) => t,
+            Tag::Object{text, ..} => text,
+        }
+    }
+}
+
+/// node image
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeImage {
+    pub url:    String,
+    pub width:  f64,
+    pub height: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit:    Option<String>,
+}
+
+/// arrow between two nodes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Arrow {
+    pub id:     String,
+    #[serde(default)]
+    pub label:  String,
+    pub from:   String,
+    pub to:     String,
+    #[serde(default)]
+    pub delta1: Delta,
+    #[serde(default)]
+    pub delta2: Delta,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bidirectional: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style:  Option<Map<String, Value>>,
+    #[serde(flatten)]
+    pub extra:  Map<String, Value>,
+}
+
+/// arrow control point offset
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Delta {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// summary of children `start..=end` of `parent`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub id:     String,
+    #[serde(default)]
+    pub label:  String,
+    pub parent: String,
+    pub start:  usize,
+    pub end:    usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style:  Option<Map<String, Value>>,
+    #[serde(flatten)]
+    pub extra:  Map<String, Value>,
+}
+
+/// theme
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Theme {
+    pub name:    String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind:    Option<String>, // light, dark
+    #[serde(default)]
+    pub palette: Vec<String>,
+    #[serde(default)]
+    pub css_var: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub extra:   Map<String, Value>,
+}
+
+impl MindMapData {
+    /// parse and validate mindmap data
+    pub fn parse(content: &str) -> Result<Self, MyError> {
+        let data: Self = match serde_json::from_str(content) {
+            Ok(d) => d,
+            Err(e) => return Err(MyError::ValidationError{reason: format!("invalid mindmap data: {}", e)}),
+        };
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// check node ids are unique, arrows and summaries reference existing nodes
+    pub fn validate(&self) -> Result<(), MyError> {
+        let mut ids: HashSet<&str> = HashSet::new();
+        for node in self.node_data.descendants() {
+            if node.id.is_empty() {
+                return Err(MyError::ValidationError{reason: format!("node with topic {:?} has empty id", node.topic)})
+            }
+            if !ids.insert(&node.id) {
+                return Err(MyError::ValidationError{reason: format!("duplicate node id {:?}", node.id)})
+            }
+        }
+        for arrow in &self.arrows {
+            for end in [&arrow.from, &arrow.to] {
+                if !ids.contains(end.as_str()) {
+                    return Err(MyError::ValidationError{reason: format!("arrow {:?} references missing node {:?}", arrow.id, end)})
+                }
+            }
+        }
+        for summary in &self.summaries {
+            match self.node_data.find(&summary.parent) {
+                Some(parent) => if summary.start > summary.end || summary.end >= parent.children.len() {
+                    return Err(MyError::ValidationError{reason: format!("summary {:?} range {}..={} out of {} children of node {:?}", summary.id, summary.start, summary.end, parent.children.len(), summary.parent)})
+                },
+                None => return Err(MyError::ValidationError{reason: format!("summary {:?} references missing node {:?}", summary.id, summary.parent)}),
+            }
+        }
+        Ok(())
+    }
+
+    /// insert `node` as child of `parent` at `index`, append if None or out of range
+    pub fn add_child(&mut self, parent: &str, node: NodeObj, index: Option<usize>) -> Result<(), MyError> {
+        let parent_node = match self.node_data.find_mut(parent) {
+            Some(p) => p,
+            None => return Err(MyError::NotFoundError{what: format!("node {:?}", parent)}),
+        };
+        let index = index.map_or(parent_node.children.len(), |i| i.min(parent_node.children.len()));
+        parent_node.children.insert(index, node);
+        self.summaries_inserted(parent, index);
+        self.validate()
+    }
+
+    /// move node `id` to be a child of `parent` at `index`, append if None or out of range
+    pub fn move_node(&mut self, id: &str, parent: &str, index: Option<usize>) -> Result<(), MyError> {
+        if self.node_data.id == id {
+            return Err(MyError::ValidationError{reason: "can not move the root node".to_string()})
+        }
+        match self.node_data.find(id) {
+            Some(node) => if node.find(parent).is_some() {
+                return Err(MyError::ValidationError{reason: format!("can not move node {:?} into itself or its descendant {:?}", id, parent)})
+            },
+            None => return Err(MyError::NotFoundError{what: format!("node {:?}", id)}),
+        }
+        if self.node_data.find(parent).is_none() {
+            return Err(MyError::NotFoundError{what: format!("node {:?}", parent)})
+        }
+        let (old_parent, old_index) = self.node_data.position(id).unwrap(); // checked above
+        let node = self.node_data.remove_descendant(id).unwrap();
+        self.summaries_removed(&old_parent, old_index);
+        self.add_child(parent, node, index)
+    }
+
+    /// keep summaries of `parent` on the same children after a child is inserted at `index`
+    /// a child inserted after the first child of a summary joins the summary
+    fn summaries_inserted(&mut self, parent: &str, index: usize) {
+        for summary in self.summaries.iter_mut().filter(|s| s.parent == parent) {
+            if index <= summary.start {
+                summary.start += 1;
+                summary.end += 1;
+            } else if index <= summary.end {
+                summary.end += 1;
+            }
+        }
+    }
+
+    /// keep summaries of `parent` on the same children after the child at `index` is removed
+    /// a summary of only the removed child is dropped
+    fn summaries_removed(&mut self, parent: &str, index: usize) {
+        self.summaries.retain_mut(|summary| {
+            if summary.parent != parent || index > summary.end {
+                true
+            } else if index < summary.start {
+                summary.start -= 1;
+                summary.end -= 1;
+                true
+            } else if summary.start == summary.end {
+                false
+            } else {
+                summary.end -= 1;
+                true
+            }
+        });
+    }
+
+    /// side of each main node, 0: left, 1: right, same as the mind-elixir layout
+    /// map direction defaults to right, in side mode main nodes without direction go to the side with fewer nodes
+    pub fn main_sides(&self) -> Vec<u8> {
+        let children = &self.node_data.children;
+        match self.direction {
+            Some(0) => vec![0; children.len()],
+            Some(2) => {
+                let (mut left, mut right) = (0, 0);
+                children.iter().map(|c| match c.direction {
+                    Some(0) => { left += 1; 0 },
+                    Some(1) => { right += 1; 1 },
+                    _ => if left <= right { left += 1; 0 } else { right += 1; 1 },
+                }).collect()
+            },
+            _ => vec![1; children.len()],
+        }
+    }
+
+    /// give a new id to every node with empty or duplicate id, used after import from other formats
+    pub fn ensure_unique_ids(&mut self) {
+        fn fix(node: &mut NodeObj, seen: &mut HashSet<String>) {
+            if node.id.is_empty() || !seen.insert(node.id.clone()) {
+                node.id = new_node_id();
+                seen.insert(node.id.clone());
+            }
+            for child in &mut node.children {
+                fix(child, seen);
+            }
+        }
+        fix(&mut self.node_data, &mut HashSet::new());
+    }
+
+    /// serialize to json string, same format as `mind.getDataString()`
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl NodeObj {
+    /// this node and all descendants, depth first
+    pub fn descendants(&self) -> Vec<&NodeObj> {
+        let mut nodes = Vec::new();
+        self.collect(&mut nodes);
+        nodes
+    }
+
+    fn collect<'a>(&'a self, nodes: &mut Vec<&'a NodeObj>) {
+        nodes.push(self);
+        for child in &self.children {
+            child.collect(nodes);
+        }
+    }
+
+    /// find node by id
+    pub fn find(&self, id: &str) -> Option<&NodeObj> {
+        if self.id == id {
+            return Some(self)
+        }
+        self.children.iter().find_map(|c| c.find(id))
+    }
+
+    /// find node by id, mutable
+    pub fn find_mut(&mut self, id: &str) -> Option<&mut NodeObj> {
+        if self.id == id {
+            return Some(self)
+        }
+        self.children.iter_mut().find_map(|c| c.find_mut(id))
+    }
+
+    /// parent id and index in its children of descendant node `id`
+    pub fn position(&self, id: &str) -> Option<(String, usize)> {
+        if let Some(i) = self.children.iter().position(|c| c.id == id) {
+            return Some((self.id.clone(), i))
+        }
+        self.children.iter().find_map(|c| c.position(id))
+    }
+
+    /// remove descendant node by id, this node itself can not be removed
+    pub fn remove_descendant(&mut self, id: &str) -> Option<NodeObj> {
+        if let Some(i) = self.children.iter().position(|c| c.id == id) {
+            return Some(self.children.remove(i))
+        }
+        self.children.iter_mut().find_map(|c| c.remove_descendant(id))
+    }
+}
+
+/// new node id, 16 hex chars like the ids generated by mind-elixir
+pub fn new_node_id() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// root with children a, b, c, d, summary `s` of b and c
+    fn mindmap() -> MindMapData {
+        MindMapData::parse(r#"{"nodeData":{"id":"root","topic":"root","children":[
+            {"id":"a","topic":"a"},{"id":"b","topic":"b"},{"id":"c","topic":"c"},{"id":"d","topic":"d"}
+        ]},"summaries":[{"id":"s","label":"s","parent":"root","start":1,"end":2}]}"#).unwrap()
+    }
+
+    fn node(id: &str) -> NodeObj {
+        NodeObj{id: id.to_string(), topic: id.to_string(), ..NodeObj::default()}
+    }
+
+    fn range(data: &MindMapData) -> Vec<(usize, usize)> {
+        data.summaries.iter().map(|s| (s.start, s.end)).collect()
+    }
+
+    #[test]
+    fn insert_shifts_or_extends_summary() {
+        let mut data = mindmap();
+        data.add_child("root", node("x"), Some(0)).unwrap();
+        assert_eq!(range(&data), [(2, 3)]);
+        data.add_child("root", node("y"), Some(3)).unwrap();
+        assert_eq!(range(&data), [(2, 4)]);
+        data.add_child("root", node("z"), None).unwrap();
+        assert_eq!(range(&data), [(2, 4)]);
+        data.add_child("a", node("w"), Some(0)).unwrap();
+        assert_eq!(range(&data), [(2, 4)]);
+    }
+
+    #[test]
+    fn move_out_shrinks_or_drops_summary() {
+        let mut data = mindmap();
+        data.move_node("c", "d", None).unwrap();
+        assert_eq!(range(&data), [(1, 1)]);
+        data.move_node("a", "d", None).unwrap();
+        assert_eq!(range(&data), [(0, 0)]);
+        data.move_node("b", "d", None).unwrap();
+        assert!(data.summaries.is_empty());
+    }
+
+    #[test]
+    fn move_inside_parent_keeps_summary_on_nodes() {
+        let mut data = mindmap();
+        data.move_node("d", "root", Some(0)).unwrap();
+        assert_eq!(range(&data), [(2, 3)]);
+        data.move_node("b", "root", Some(0)).unwrap();
+        assert_eq!(range(&data), [(3, 3)]);
+    }
+}
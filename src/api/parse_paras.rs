@@ -6,16 +6,21 @@ use std::process::exit;
 use argh::FromArgs;
 use once_cell::sync::Lazy;
 use ron::{
-    de::from_str,
+    extensions::Extensions,
     error::{
         SpannedError,
         Error as ron_error,
         Position,
     },
+    Options,
 };
 use serde::Deserialize;
 
-use crate::error::MyError;
+use crate::{
+    convert::Format,
+    error::MyError,
+    map_id::MapId,
+};
 
 /// global para
 pub static PARAS: Lazy<ParsedParas> = Lazy::new(|| {
@@ -48,9 +53,57 @@ struct Paras {
     #[argh(option, short = 'o')]
     outpath: Option<String>,
 
-    /// config file, the priority of -a/-p/-l/-o is higher than -c, default: mindmap_config.txt in current path or binary executable file path
+    /// autosave interval in seconds, 0 means disable, default: 60
+    #[argh(option, short = 's')]
+    autosave: Option<u64>,
+
+    /// save mindmap to output path on every record, `-w true` or `-w false` overrides the config file, default: false
+    #[argh(option, short = 'w')]
+    write_through: Option<bool>,
+
+    /// max number of history revisions kept for each mindmap, 0 means unlimited, default: 50
+    #[argh(option, short = 'n')]
+    history_count: Option<usize>,
+
+    /// max age in days of history revisions, 0 means unlimited, default: 0
+    #[argh(option, short = 'd')]
+    history_days: Option<u64>,
+
+    /// config file, the priority of -a/-p/-l/-o/-s/-w/-n/-d/--font is higher than -c, default: mindmap_config.txt in current path or binary executable file path
     #[argh(option, short = 'c')]
     config: Option<String>,
+
+    /// font file or directory for png and pdf, e.g. a CJK font on a server without one, default: system fonts and embedded Noto Sans (and Noto Sans SC with `--features cjk-font`)
+    #[argh(option)]
+    font: Option<String>,
+
+    /// export the mindmap of this uuid in output path and exit without starting the server
+    #[argh(option, short = 'e')]
+    export: Option<String>,
+
+    /// render the mindmap of this uuid in output path as svg, png or pdf by -f and exit without starting the server
+    #[argh(option, short = 'r')]
+    render: Option<String>,
+
+    /// export or import format, support: html, md, opml, mm, xmind, plantuml, mermaid, viewer, dot, svg, png and pdf (the last five export only), default: md for export, svg for render, file extension for import
+    #[argh(option, short = 'f')]
+    format: Option<String>,
+
+    /// markdown export, levels written as headings, deeper levels are bullet lists, default: 2
+    #[argh(option)]
+    heading_depth: Option<usize>,
+
+    /// export or render to this file, default: print to stdout
+    #[argh(option)]
+    export_to: Option<String>,
+
+    /// import this file as new mindmap in output path, one for each XMind sheet, print their uuids and exit without starting the server
+    #[argh(option, short = 'i')]
+    import: Option<String>,
+
+    /// label of the imported mindmap
+    #[argh(option)]
+    label: Option<String>,
 }
 
 /// mindmap_config.txt
@@ -60,25 +113,47 @@ struct Config {
     port:     u16,
     language: String,
     outpath:  String,
+    #[serde(default)]
+    autosave: Option<u64>, // optional, default: 60
+    #[serde(default)]
+    write_through: Option<bool>, // optional, default: false
+    #[serde(default)]
+    history_count: Option<usize>, // optional, default: 50
+    #[serde(default)]
+    history_days: Option<u64>, // optional, default: 0
+    #[serde(default)]
+    font: Option<String>, // optional, default: None
 }
 
 /// parsed para
 ///#[derive(Debug, Default)]
 pub struct ParsedParas {
-    pub addr:      [u8; 4], // addr, default: 127.0.0.1
-    pub addr_str:  String,  // addr string, default: "127.0.0.1"
-    pub port:      u16,     // port, default: 8081
-    pub language:  String,  // language, support: zh_CN, zh_TW, en, ja, pt, ru, default: en
-    pub outpath:   PathBuf, // output path, default: ./mindmap
+    pub addr:          [u8; 4], // addr, default: 127.0.0.1
+    pub addr_str:      String,  // addr string, default: "127.0.0.1"
+    pub port:          u16,     // port, default: 8081
+    pub language:      String,  // language, support: zh_CN, zh_TW, en, ja, pt, ru, default: en
+    pub outpath:       PathBuf, // output path, default: ./mindmap
+    pub autosave:      u64,     // autosave interval in seconds, 0 means disable, default: 60
+    pub write_through: bool,    // save mindmap to output path on every `/update`, default: false
+    pub history_count: usize,   // max number of history revisions kept for each mindmap, 0 means unlimited, default: 50
+    pub history_days:  u64,     // max age in days of history revisions, 0 means unlimited, default: 0
+    pub font:          Option<PathBuf>, // font file or directory for png and pdf, default: None
+    pub export:        Option<MapId>,   // export this mindmap and exit, default: None
+    pub render:        Option<MapId>,   // render this mindmap as svg and exit, default: None
+    pub format:        Option<Format>,  // export or import format, default: None
+    pub heading_depth: usize,           // markdown export heading depth, default: 2
+    pub export_to:     Option<PathBuf>, // export file, default: None, print to stdout
+    pub import:        Option<PathBuf>, // import this file and exit, default: None
+    pub label:         Option<String>,  // label of the imported mindmap, default: None
 }
 
 /// 解析参数
 pub fn parse_para() -> Result<ParsedParas, MyError> {
-    let para: Paras = argh::from_env();
+    let para: Paras = args();
     // get config file
-    let config: Option<Config> = if para.addr.is_some() && para.port.is_some() && para.language.is_some() && para.outpath.is_some() {
+    let config: Option<Config> = if para.addr.is_some() && para.port.is_some() && para.language.is_some() && para.outpath.is_some() && para.autosave.is_some() && para.write_through.is_some() && para.history_count.is_some() && para.history_days.is_some() && para.font.is_some() {
         if para.config.is_some() {
-            println!("Warning - the priority of -a/-p/-l/-o is higher than -c, your -c is useless.");
+            println!("Warning - the priority of -a/-p/-l/-o/-s/-w/-n/-d/--font is higher than -c, your -c is useless.");
         }
         None
     } else {
@@ -93,7 +168,8 @@ pub fn parse_para() -> Result<ParsedParas, MyError> {
             None => find_config_file(),
         };
         if let Some(f) = config_file {
-            match from_str::<Config>(&read_to_string(&f)?) {
+            // optional fields are written as `autosave: 60`, not `autosave: Some(60)`
+            match Options::default().with_default_extension(Extensions::IMPLICIT_SOME).from_str::<Config>(&read_to_string(&f)?) {
                 Ok(c) => Some(c),
                 Err(e) => if let SpannedError{code: ron_error::Message(m), position: Position{line, col}} = e {
                     return Err(MyError::ParaError{para: format!("{} position: line={}, column={}", m, line, col)})
@@ -105,7 +181,7 @@ pub fn parse_para() -> Result<ParsedParas, MyError> {
             None
         }
     };
-    // parse -a, -p, -o
+    // parse -a, -p, -l, -o, -s, -w, -n, -d, --font
     let out: ParsedParas = ParsedParas{
         addr: match &para.addr {
             Some(a) => get_addr(a)?,
@@ -157,11 +233,50 @@ pub fn parse_para() -> Result<ParsedParas, MyError> {
                 }
             },
         },
+        autosave: match para.autosave {
+            Some(s) => s,
+            None => config.as_ref().and_then(|c| c.autosave).unwrap_or(60),
+        },
+        write_through: match para.write_through {
+            Some(w) => w,
+            None => config.as_ref().and_then(|c| c.write_through).unwrap_or(false),
+        },
+        history_count: match para.history_count {
+            Some(n) => n,
+            None => config.as_ref().and_then(|c| c.history_count).unwrap_or(50),
+        },
+        history_days: match para.history_days {
+            Some(d) => d,
+            None => config.as_ref().and_then(|c| c.history_days).unwrap_or(0),
+        },
+        font: para.font.or_else(|| config.as_ref().and_then(|c| c.font.clone())).map(PathBuf::from),
+        export: match para.export {
+            Some(e) => Some(e.parse()?),
+            None => None,
+        },
+        render: match para.render {
+            Some(r) => Some(r.parse()?),
+            None => None,
+        },
+        format: match para.format {
+            Some(f) => Some(f.parse()?),
+            None => None,
+        },
+        heading_depth: para.heading_depth.unwrap_or(2),
+        export_to: para.export_to.map(PathBuf::from),
+        import: para.import.map(PathBuf::from),
+        label: para.label.filter(|l| !l.is_empty()),
     };
     // check language
     if !["zh_CN", "zh_TW", "en", "ja", "pt", "ru"].iter().any(|l| l == &out.language) {
         return Err(MyError::ParaError{para: format!("-l only support zh_CN, zh_TW, en, ja, pt, ru, not {}", out.language)})
     }
+    // check font exist
+    if let Some(f) = &out.font {
+        if !f.exists() {
+            return Err(MyError::FileNotExistError{file: f.display().to_string()})
+        }
+    }
     // check outpath exist
     if !(out.outpath.exists() && out.outpath.is_dir()) {
         if let Err(err) = create_dir_all(&out.outpath) {
@@ -171,6 +286,19 @@ pub fn parse_para() -> Result<ParsedParas, MyError> {
     Ok(out)
 }
 
+/// command line arguments of the program
+#[cfg(not(test))]
+fn args() -> Paras {
+    argh::from_env()
+}
+
+/// tests ignore the arguments of the test binary, e.g. test name filter, and save to a temporary output path
+#[cfg(test)]
+fn args() -> Paras {
+    let outpath = std::env::temp_dir().join("mindmap-test");
+    Paras::from_args(&["mindmap"], &["-o", outpath.to_str().unwrap()]).unwrap()
+}
+
 /// parse ip addr
 fn get_addr(addr: &str) -> Result<[u8;4], MyError> {
     let tmp_addr_vec: Vec<&str> = addr.split(".").collect();
@@ -184,7 +312,7 @@ fn get_addr(addr: &str) -> Result<[u8;4], MyError> {
         }
         Ok(tmp_addr)
     } else {
-        return Err(MyError::ParaError{para: format!("-a ip address must be x.x.x.x format, not {}", addr)})
+        Err(MyError::ParaError{para: format!("-a ip address must be x.x.x.x format, not {}", addr)})
     }
 }
 
@@ -1,11 +1,20 @@
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::Router;
 
 use crate::api::handlers::{
     index::index,
     update::update,
-    previous::previous,
+    previous::{previous, permalink},
     download::download,
+    history::{history, history_view, history_restore},
+    mindmap::{delete_mindmap, archive, unarchive},
+    trash::{trash, trash_restore, trash_purge},
+    api::{api_list, api_get, api_create, api_replace, api_patch, api_delete},
+    nodes::{node_get, node_add_child, node_patch, node_move},
+    search::{search_page, api_search},
+    maps::maps,
+    import::api_import,
+    render::render,
     fallback::fallback,
 };
 
@@ -18,6 +27,26 @@ pub fn configure() -> Router {
         .route("/", get(index)) // GET `/`
         .route("/update", post(update)) // POST `/update`
         .route("/previous", get(previous)) // GET `/previous`
+        .route("/m/:uuid/:node_id", get(permalink)) // GET `/m/:uuid/:node_id`
         .route("/download/:uuid", get(download)) // GET `/download/:uuid`
+        .route("/history/:uuid", get(history)) // GET `/history/:uuid`
+        .route("/history/:uuid/:rev", get(history_view)) // GET `/history/:uuid/:rev`
+        .route("/history/:uuid/:rev/restore", post(history_restore)) // POST `/history/:uuid/:rev/restore`
+        .route("/mindmap/:uuid", delete(delete_mindmap)) // DELETE `/mindmap/:uuid`
+        .route("/mindmap/:uuid/archive", post(archive)) // POST `/mindmap/:uuid/archive`
+        .route("/mindmap/:uuid/unarchive", post(unarchive)) // POST `/mindmap/:uuid/unarchive`
+        .route("/trash", get(trash)) // GET `/trash`
+        .route("/trash/:uuid", delete(trash_purge)) // DELETE `/trash/:uuid`
+        .route("/trash/:uuid/restore", post(trash_restore)) // POST `/trash/:uuid/restore`
+        .route("/api/v1/mindmaps", get(api_list).post(api_create)) // GET, POST `/api/v1/mindmaps`
+        .route("/api/v1/mindmaps/:uuid", get(api_get).put(api_replace).patch(api_patch).delete(api_delete)) // GET, PUT, PATCH, DELETE `/api/v1/mindmaps/:uuid`
+        .route("/api/v1/mindmaps/:uuid/nodes/:node_id", get(node_get).patch(node_patch)) // GET, PATCH `/api/v1/mindmaps/:uuid/nodes/:node_id`
+        .route("/api/v1/mindmaps/:uuid/nodes/:node_id/children", post(node_add_child)) // POST `/api/v1/mindmaps/:uuid/nodes/:node_id/children`
+        .route("/api/v1/mindmaps/:uuid/nodes/:node_id/move", post(node_move)) // POST `/api/v1/mindmaps/:uuid/nodes/:node_id/move`
+        .route("/maps", get(maps)) // GET `/maps`
+        .route("/search", get(search_page)) // GET `/search`
+        .route("/api/v1/search", get(api_search)) // GET `/api/v1/search`
+        .route("/api/v1/import", post(api_import)) // POST `/api/v1/import`
+        .route("/render/:file", get(render)) // GET `/render/:uuid.svg`, `.png` or `.pdf`
         .fallback(fallback) // not match any router
 }
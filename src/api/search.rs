@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+    map_id::MapId,
+    model::{MindMapData, NodeObj},
+};
+
+/// one searchable text of a mindmap, the label or one node
+struct Entry {
+    node_id: Option<String>, // None for the label
+    path:    Vec<String>, // topics from root to this node
+    text:    String, // label, or topic and tags
+}
+
+/// one search result
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub uuid:      MapId,
+    pub label:     Option<String>,
+    pub node_id:   Option<String>, // None if the label matched
+    pub node_path: Vec<String>,
+    pub snippet:   String,
+}
+
+/// in-memory inverted index over node topics, tags and labels
+/// built on the first search, then kept up to date by `MindMap`
+#[derive(Default)]
+pub struct SearchIndex {
+    built:    bool,
+    docs:     HashMap<MapId, (Option<String>, Vec<Entry>)>, // key: uuid, value: (label, entries)
+    postings: BTreeMap<String, HashSet<(MapId, usize)>>, // key: token, value: (uuid, entry index)
+}
+
+/// CJK characters have no spaces between words, index them one by one
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{ac00}'..='\u{d7af}' | '\u{f900}'..='\u{faff}')
+}
+
+/// split text into lowercase tokens, words of letters and digits, or single CJK characters
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// part of `text` around the first occurrence of `token`, at most about 120 chars
+fn snippet(text: &str, token: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= 120 {
+        return text.to_string()
+    }
+    // lowercase char by char, so char index in `lower` is the same as in `chars`
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let token: Vec<char> = token.chars().collect();
+    let pos = lower.windows(token.len().max(1)).position(|w| w == token.as_slice()).unwrap_or(0);
+    let start = pos.saturating_sub(40);
+    let end = (start + 120).min(chars.len());
+    format!(
+        "{}{}{}",
+        if start > 0 { "…" } else { "" },
+        chars[start..end].iter().collect::<String>(),
+        if end < chars.len() { "…" } else { "" },
+    )
+}
+
+/// collect node entries depth first, `path` is the topics of ancestors
+fn collect_entries(node: &NodeObj, path: &mut Vec<String>, entries: &mut Vec<Entry>) {
+    path.push(node.topic.clone());
+    let mut text = node.topic.clone();
+    for tag in &node.tags {
+        text.push(' ');
+        text.push_str(tag.text());
+    }
+    entries.push(Entry{node_id: Some(node.id.clone()), path: path.clone(), text});
+    for child in &node.children {
+        collect_entries(child, path, entries);
+    }
+    path.pop();
+}
+
+impl SearchIndex {
+    /// whether all mindmap have been indexed
+    pub fn is_built(&self) -> bool {
+        self.built
+    }
+
+    /// mark index as built, after all mindmap have been added by `index_map`
+    pub fn set_built(&mut self) {
+        self.built = true;
+    }
+
+    /// add or replace one mindmap, mindmap data that can not be parsed only indexes the label
+    pub fn index_map(&mut self, uuid: &MapId, content: &str, label: Option<&str>) {
+        self.remove_map(uuid);
+        let mut entries = Vec::new();
+        if let Some(l) = label {
+            entries.push(Entry{node_id: None, path: Vec::new(), text: l.to_string()});
+        }
+        if let Ok(data) = serde_json::from_str::<MindMapData>(content) {
+            collect_entries(&data.node_data, &mut Vec::new(), &mut entries);
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            for token in tokenize(&entry.text) {
+                self.postings.entry(token).or_default().insert((uuid.clone(), i));
+            }
+        }
+        self.docs.insert(uuid.clone(), (label.map(|l| l.to_string()), entries));
+    }
+
+    /// remove one mindmap, e.g. moved to trash
+    pub fn remove_map(&mut self, uuid: &MapId) {
+        if let Some((_, entries)) = self.docs.remove(uuid) {
+            for (i, entry) in entries.iter().enumerate() {
+                for token in tokenize(&entry.text) {
+                    if let Some(set) = self.postings.get_mut(&token) {
+                        set.remove(&(uuid.clone(), i));
+                        if set.is_empty() {
+                            self.postings.remove(&token);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// entries containing all query tokens, each query token matches as a prefix, e.g. `roll` matches `rollout`
+    /// entries containing the whole query text come first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let tokens = tokenize(query);
+        if tokens.is_empty() {
+            return Vec::new()
+        }
+        let mut found: Option<HashSet<&(MapId, usize)>> = None;
+        for token in &tokens {
+            let matched: HashSet<&(MapId, usize)> = self.postings
+                .range(token.clone()..)
+                .take_while(|(k, _)| k.starts_with(token.as_str()))
+                .flat_map(|(_, v)| v.iter())
+                .collect();
+            found = Some(match found {
+                Some(f) => f.intersection(&matched).copied().collect(),
+                None => matched,
+            });
+        }
+        let query_lower = query.trim().to_lowercase();
+        let mut hits: Vec<(bool, &MapId, usize)> = found.unwrap_or_default().into_iter().map(|(uuid, i)| {
+            let phrase = self.docs[uuid].1[*i].text.to_lowercase().contains(&query_lower);
+            (!phrase, uuid, *i)
+        }).collect();
+        hits.sort_by(|a, b| (a.0, a.1.as_str(), a.2).cmp(&(b.0, b.1.as_str(), b.2)));
+        hits.into_iter().take(limit).map(|(_, uuid, i)| {
+            let (label, entries) = &self.docs[uuid];
+            let entry = &entries[i];
+            SearchHit{
+                uuid:      uuid.clone(),
+                label:     label.clone(),
+                node_id:   entry.node_id.clone(),
+                node_path: entry.path.clone(),
+                snippet:   snippet(&entry.text, &tokens[0]),
+            }
+        }).collect()
+    }
+}
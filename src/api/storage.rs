@@ -0,0 +1,90 @@
+use std::fs::{File, OpenOptions, read_to_string, remove_file, rename};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tracing::{event, Level};
+
+use crate::error::MyError;
+
+/// `<file>.tmp` in the same directory, e.g. `xxx/.f66bedbd-9972-4ec3-9a30-9510d4fffe1c.json.tmp`
+fn tmp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+/// `<file>.bak` in the same directory, e.g. `xxx/f66bedbd-9972-4ec3-9a30-9510d4fffe1c.json.bak`
+pub fn bak_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    path.with_file_name(format!("{}.bak", file_name))
+}
+
+/// fsync the directory, so the rename is persisted
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Err(e) = File::open(dir).and_then(|d| d.sync_all()) {
+        event!(Level::WARN, "fsync dir {}: {}", dir.display(), e);
+    }
+}
+
+/// windows can not open a directory as a file, the rename is already durable there
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+/// write content to a temp file in the same directory, fsync, then rename to `path`
+/// a crash at any moment leaves either the old file or the new file, never a truncated one
+pub fn atomic_write(path: &Path, content: impl AsRef<[u8]>) -> Result<(), MyError> {
+    let tmp = tmp_path(path);
+    let write_tmp = || -> std::io::Result<()> {
+        let mut f = OpenOptions::new().write(true).create(true).truncate(true).open(&tmp)?;
+        f.write_all(content.as_ref())?;
+        f.sync_all()
+    };
+    if let Err(e) = write_tmp() {
+        let _ = remove_file(&tmp);
+        return Err(MyError::WriteFileError{file: tmp.display().to_string(), error: e})
+    }
+    if let Err(e) = rename(&tmp, path) {
+        let _ = remove_file(&tmp);
+        return Err(MyError::WriteFileError{file: path.display().to_string(), error: e})
+    }
+    if let Some(dir) = path.parent() {
+        sync_dir(dir);
+    }
+    Ok(())
+}
+
+/// same as `atomic_write`, but keep the previous good copy as `<file>.bak`
+pub fn atomic_write_with_backup(path: &Path, content: impl AsRef<[u8]>) -> Result<(), MyError> {
+    if path.is_file() && read_json(path).is_ok() {
+        let bak = bak_path(path);
+        if let Err(e) = rename(path, &bak) {
+            return Err(MyError::WriteFileError{file: bak.display().to_string(), error: e})
+        }
+    }
+    atomic_write(path, content)
+}
+
+/// read a file and check it is valid json
+fn read_json(path: &Path) -> Result<String, MyError> {
+    let content = read_to_string(path)?;
+    match serde_json::from_str::<serde_json::Value>(&content) {
+        Ok(_) => Ok(content),
+        Err(e) => Err(MyError::ParseJsonError{file: path.display().to_string(), error: e}),
+    }
+}
+
+/// read mindmap json, fall back to `<uuid>.json.bak` when the primary is missing or fails to parse
+pub fn read_mindmap_json(path: &Path) -> Result<String, MyError> {
+    match read_json(path) {
+        Ok(content) => Ok(content),
+        Err(e) => {
+            let bak = bak_path(path);
+            if bak.is_file() {
+                event!(Level::WARN, "{}, fall back to {}", e, bak.display());
+                read_json(&bak)
+            } else {
+                Err(e)
+            }
+        },
+    }
+}
@@ -9,15 +9,38 @@ use mindmap::{
     parse_paras::PARAS,
     router::configure,
     ctrlc::wait_for_signal,
+    autosave::{autosave, save_all},
+    convert::{run_export, run_import, run_render, Format},
 };
 
 #[tokio::main]
 async fn main() {
-    // listen `ctrl-c`
-    tokio::spawn(async {
-        wait_for_signal().await;
-        exit(1);
-    });
+    // export mode, write one mindmap and exit without starting the server
+    if let Some(uuid) = &PARAS.export {
+        if let Err(e) = run_export(uuid, PARAS.format.unwrap_or(Format::Md)) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return
+    }
+
+    // render mode, write one mindmap as image and exit without starting the server
+    if let Some(uuid) = &PARAS.render {
+        if let Err(e) = run_render(uuid) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return
+    }
+
+    // import mode, create one mindmap and exit without starting the server
+    if let Some(file) = &PARAS.import {
+        if let Err(e) = run_import(file) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return
+    }
 
     // Start tracing
     tracing_subscriber::fmt() // INFO, WARN, ERROR, https://github.com/tokio-rs/tracing/blob/master/examples/examples/hyper-echo.rs
@@ -26,6 +49,9 @@ async fn main() {
         .init();
     event!(Level::INFO, "Running on http://{}:{}", PARAS.addr_str, PARAS.port);
 
+    // periodically save updated mindmap
+    tokio::spawn(autosave());
+
     // addr and port
     let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(PARAS.addr[0], PARAS.addr[1], PARAS.addr[2], PARAS.addr[3])), PARAS.port);
     // create router
@@ -38,9 +64,16 @@ async fn main() {
             exit(1);
         },
     };
-    // start http
-    if let Err(e) = axum::serve(listener, router.into_make_service()).await {
+    // start http, stop accepting on `ctrl-c` or SIGTERM, then wait in-flight requests
+    if let Err(e) = axum::serve(listener, router.into_make_service()).with_graceful_shutdown(wait_for_signal()).await {
         println!("{}", e);
         exit(1);
     }
+
+    // save all updated mindmap, exit with 1 only when some mindmap failed to save
+    let (saved, failed) = save_all().await;
+    event!(Level::INFO, "shutdown: {} mindmap saved, {} failed", saved, failed);
+    if failed > 0 {
+        exit(1);
+    }
 }